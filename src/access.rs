@@ -0,0 +1,403 @@
+use std::{
+  collections::HashMap,
+  fs,
+  net::{ IpAddr, Ipv4Addr, Ipv6Addr },
+  path::{ Path, PathBuf },
+  sync::{ Mutex, MutexGuard },
+  time::SystemTime,
+};
+use chrono::{ DateTime, FixedOffset, Utc };
+use serde::{ Deserialize, de::DeserializeOwned };
+use simplelog::{ info, debug, warn };
+
+use crate::metrics::BlockReason;
+use crate::settings::Settings;
+
+/// A single address or a CIDR block of `blackListAddress`.
+#[derive(Clone, Copy, Debug)]
+enum AddressRange {
+  V4 { network: u32, prefix: u32 },
+  V6 { network: u128, prefix: u32 },
+}
+
+impl AddressRange {
+  /// Parses `1.2.3.4`, `1.2.0.0/16`, `2001:db8::1` or `2001:db8::/32`. IPv4-mapped IPv6 ranges are turned
+  /// into their IPv4 equivalent.
+  fn parse(entry: &str) -> Option<Self> {
+    let (address, prefix) = match entry.split_once('/') {
+      Some((address, prefix)) => {
+        let prefix: &str = prefix.trim();
+        // `parse` would take a leading `+` too
+        if prefix.is_empty() || !prefix.bytes().all(|byte| byte.is_ascii_digit()) {
+          return None;
+        }
+        (address.trim(), Some(prefix.parse::<u32>().ok()?))
+      }
+      None => (entry, None),
+    };
+
+    match address.parse::<IpAddr>().ok()? {
+      IpAddr::V4(address) => Self::v4(address, prefix.unwrap_or(32)),
+      IpAddr::V6(address) => {
+        let prefix: u32 = prefix.unwrap_or(128);
+        match address.to_ipv4_mapped() {
+          Some(address) if prefix >= 96 => Self::v4(address, prefix - 96),
+          _ => Self::v6(address, prefix),
+        }
+      }
+    }
+  }
+
+  fn v4(address: Ipv4Addr, prefix: u32) -> Option<Self> {
+    if prefix > 32 {
+      return None;
+    }
+    Some(AddressRange::V4 { network: u32::from(address) & v4_mask(prefix), prefix })
+  }
+
+  fn v6(address: Ipv6Addr, prefix: u32) -> Option<Self> {
+    if prefix > 128 {
+      return None;
+    }
+    Some(AddressRange::V6 { network: u128::from(address) & v6_mask(prefix), prefix })
+  }
+
+  fn contains(&self, address: &IpAddr) -> bool {
+    match (self, normalize(address)) {
+      (AddressRange::V4 { network, prefix }, IpAddr::V4(address)) => {
+        (u32::from(address) & v4_mask(*prefix)) == *network
+      }
+      (AddressRange::V6 { network, prefix }, IpAddr::V6(address)) => {
+        (u128::from(address) & v6_mask(*prefix)) == *network
+      }
+      _ => false,
+    }
+  }
+}
+
+fn v4_mask(prefix: u32) -> u32 {
+  u32::MAX.checked_shl(32 - prefix).unwrap_or(0)
+}
+
+fn v6_mask(prefix: u32) -> u128 {
+  u128::MAX.checked_shl(128 - prefix).unwrap_or(0)
+}
+
+/// IPv4 clients of a dual stack socket show up as `::ffff:1.2.3.4`, they are matched as plain IPv4.
+fn normalize(address: &IpAddr) -> IpAddr {
+  match address {
+    IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(*address),
+    IpAddr::V4(_) => *address,
+  }
+}
+
+/// Files of the Minecraft server read by `syncMinecraftLists`, in `minecraftWorkingDirectory`.
+const MINECRAFT_LIST_FILES: [&str; 5] = [
+  "whitelist.json",
+  "ops.json",
+  "banned-players.json",
+  "banned-ips.json",
+  "server.properties",
+];
+
+/// Reason Minecraft gives to bans without one.
+const DEFAULT_BAN_REASON: &str = "Banned by an operator.";
+
+#[derive(Deserialize)]
+struct ListedPlayer {
+  name: String,
+}
+
+#[derive(Deserialize)]
+struct BannedPlayer {
+  name: String,
+  expires: Option<String>,
+  reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BannedIp {
+  ip: String,
+  expires: Option<String>,
+  reason: Option<String>,
+}
+
+/// A ban of `banned-players.json` or `banned-ips.json`.
+struct Ban {
+  reason: String,
+  /// `None` for permanent bans.
+  expires: Option<DateTime<FixedOffset>>,
+}
+
+impl Ban {
+  fn new(reason: Option<String>, expires: Option<String>) -> Self {
+    Self {
+      reason: reason.unwrap_or_else(|| String::from(DEFAULT_BAN_REASON)),
+      // "forever", or a date like "2024-01-31 12:00:00 +0000"
+      expires: expires.and_then(|expires| DateTime::parse_from_str(&expires, "%Y-%m-%d %H:%M:%S %z").ok()),
+    }
+  }
+
+  fn is_active(&self) -> bool {
+    self.expires.is_none_or(|expires| expires > Utc::now())
+  }
+}
+
+/// The lists of the Minecraft server, as last read.
+#[derive(Default)]
+struct MinecraftLists {
+  modified: HashMap<&'static str, Option<SystemTime>>,
+  /// `white-list` of `server.properties`, or whether `whitelist.json` has anyone when it can't be read.
+  whitelist_enabled: bool,
+  /// Lowercase names from `whitelist.json` and `ops.json`.
+  whitelist: Vec<String>,
+  /// By lowercase name.
+  banned_players: HashMap<String, Ban>,
+  banned_ips: HashMap<IpAddr, Ban>,
+}
+
+/// Why a player can't wake the server.
+pub enum PlayerRefusal {
+  NotWhitelisted,
+  /// Carries the ban reason.
+  Banned(String),
+}
+
+/// Decides who may reach the sleeping servers, shared by the Java, Bedrock and web listeners.
+pub struct AccessControl {
+  blacklist: Vec<AddressRange>,
+  /// Lowercase `whiteListedNames`, `None` lets everyone in.
+  whitelist: Option<Vec<String>>,
+  /// Folder of the Minecraft lists when `syncMinecraftLists` is enabled.
+  lists_directory: Option<PathBuf>,
+  lists: Mutex<MinecraftLists>,
+}
+
+impl AccessControl {
+  /// Invalid `blackListAddress` entries are logged and ignored. The Minecraft lists are read right away if
+  /// `syncMinecraftLists` is enabled.
+  pub fn new(settings: &Settings) -> Self {
+    let blacklist: Vec<AddressRange> = settings.blackListAddress
+      .iter()
+      .flatten()
+      .filter_map(|entry| {
+        let range: Option<AddressRange> = AddressRange::parse(entry.trim());
+        if range.is_none() {
+          warn!("[Container] Ignoring invalid blackListAddress entry {:?}", entry);
+        }
+        range
+      })
+      .collect();
+
+    let whitelist: Option<Vec<String>> = settings.whiteListedNames
+      .as_ref()
+      .filter(|names| !names.is_empty())
+      .map(|names| names.iter().map(|name| name.trim().to_lowercase()).collect());
+
+    let lists_directory: Option<PathBuf> = if settings.syncMinecraftLists.unwrap_or(false) {
+      Some(PathBuf::from(settings.minecraftWorkingDirectory.as_deref().unwrap_or(".")))
+    } else {
+      None
+    };
+
+    let access: Self = Self { blacklist, whitelist, lists_directory, lists: Mutex::new(MinecraftLists::default()) };
+    access.reload_minecraft_lists();
+    access
+  }
+
+  /// Whether `syncMinecraftLists` is enabled, `reload_minecraft_lists` has to be called from time to time then.
+  pub fn syncs_minecraft_lists(&self) -> bool {
+    self.lists_directory.is_some()
+  }
+
+  /// Reads the Minecraft lists again if any of them changed since last time, returns the files that changed.
+  pub fn reload_minecraft_lists(&self) -> Vec<&'static str> {
+    let directory: &Path = match &self.lists_directory {
+      Some(directory) => directory,
+      None => {
+        return Vec::new();
+      }
+    };
+
+    let mut lists: MutexGuard<MinecraftLists> = self.lists.lock().unwrap();
+    let changed: Vec<&'static str> = MINECRAFT_LIST_FILES.into_iter()
+      .filter(|file| {
+        let modified: Option<SystemTime> = fs::metadata(directory.join(file))
+          .and_then(|metadata| metadata.modified())
+          .ok();
+        lists.modified.insert(file, modified) != Some(modified)
+      })
+      .collect();
+    if changed.is_empty() {
+      return changed;
+    }
+
+    let whitelist: Vec<ListedPlayer> = read_list(directory, "whitelist.json");
+    let ops: Vec<ListedPlayer> = read_list(directory, "ops.json");
+    lists.whitelist_enabled = match read_whitelist_property(directory) {
+      Some(enabled) => enabled,
+      None => !whitelist.is_empty(),
+    };
+    lists.whitelist = whitelist.into_iter().chain(ops).map(|player| player.name.to_lowercase()).collect();
+
+    lists.banned_players = read_list::<BannedPlayer>(directory, "banned-players.json")
+      .into_iter()
+      .map(|ban| (ban.name.to_lowercase(), Ban::new(ban.reason, ban.expires)))
+      .collect();
+    lists.banned_ips = read_list::<BannedIp>(directory, "banned-ips.json")
+      .into_iter()
+      .filter_map(|ban| {
+        let address: IpAddr = normalize(&ban.ip.parse().ok()?);
+        Some((address, Ban::new(ban.reason, ban.expires)))
+      })
+      .collect();
+
+    info!(
+      "[Container] Minecraft lists: whitelist {} with {} player(s), {} banned player(s), {} banned address(es)",
+      if lists.whitelist_enabled { "enabled" } else { "disabled" },
+      lists.whitelist.len(),
+      lists.banned_players.len(),
+      lists.banned_ips.len()
+    );
+    changed
+  }
+
+  /// Checks `address` against `blackListAddress` and `banned-ips.json`, refused addresses are dropped before
+  /// anything is answered.
+  pub fn check_address(&self, address: &IpAddr) -> Result<(), BlockReason> {
+    if self.blacklist.iter().any(|range| range.contains(address)) {
+      return Err(BlockReason::Blacklist);
+    }
+    let lists: MutexGuard<MinecraftLists> = self.lists.lock().unwrap();
+    match lists.banned_ips.get(&normalize(address)) {
+      Some(ban) if ban.is_active() => Err(BlockReason::Banned),
+      _ => Ok(()),
+    }
+  }
+
+  /// Whether a whitelist applies, players the sleeping servers can't name can't wake the server then.
+  pub fn has_whitelist(&self) -> bool {
+    self.whitelist.is_some() || self.lists.lock().unwrap().whitelist_enabled
+  }
+
+  /// Checks whether the player may wake the server, against `whiteListedNames` and the Minecraft lists.
+  /// Minecraft names are case insensitive.
+  pub fn check_player(&self, name: &str) -> Result<(), PlayerRefusal> {
+    let name: String = name.to_lowercase();
+    let lists: MutexGuard<MinecraftLists> = self.lists.lock().unwrap();
+
+    if let Some(ban) = lists.banned_players.get(&name).filter(|ban| ban.is_active()) {
+      return Err(PlayerRefusal::Banned(ban.reason.clone()));
+    }
+
+    let in_settings: Option<bool> = self.whitelist.as_ref().map(|whitelist| whitelist.contains(&name));
+    let in_lists: Option<bool> = Some(lists.whitelist.contains(&name)).filter(|_| lists.whitelist_enabled);
+    match (in_settings, in_lists) {
+      (None, None) | (Some(true), _) | (_, Some(true)) => Ok(()),
+      _ => Err(PlayerRefusal::NotWhitelisted),
+    }
+  }
+}
+
+/// Entries of a Minecraft list, empty if the file is missing or broken.
+fn read_list<T: DeserializeOwned>(directory: &Path, file: &str) -> Vec<T> {
+  let content: String = match fs::read_to_string(directory.join(file)) {
+    Ok(content) => content,
+    Err(e) => {
+      debug!("[Container] Can't read {}: {}", file, e);
+      return Vec::new();
+    }
+  };
+  match serde_json::from_str(&content) {
+    Ok(list) => list,
+    Err(e) => {
+      warn!("[Container] Ignoring {}, it is not a valid list: {}", file, e);
+      Vec::new()
+    }
+  }
+}
+
+/// `white-list` of `server.properties`, `None` if the file can't be read.
+fn read_whitelist_property(directory: &Path) -> Option<bool> {
+  let properties: String = fs::read_to_string(directory.join("server.properties")).ok()?;
+  let enabled: bool = properties
+    .lines()
+    .filter_map(|line| line.split_once('='))
+    .find(|(key, _)| key.trim() == "white-list")
+    .is_some_and(|(_, value)| value.trim() == "true");
+  Some(enabled)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::IpAddr;
+  use super::AddressRange;
+
+  fn contains(range: &str, address: &str) -> bool {
+    let address: IpAddr = address.parse().unwrap();
+    AddressRange::parse(range).unwrap().contains(&address)
+  }
+
+  #[test]
+  fn parses_bare_addresses_as_single_hosts() {
+    assert!(contains("1.2.3.4", "1.2.3.4"));
+    assert!(!contains("1.2.3.4", "1.2.3.5"));
+    assert!(contains("2001:db8::1", "2001:db8::1"));
+    assert!(!contains("2001:db8::1", "2001:db8::2"));
+  }
+
+  #[test]
+  fn matches_prefix_bounds() {
+    assert!(contains("0.0.0.0/0", "1.2.3.4"));
+    assert!(contains("0.0.0.0/0", "255.255.255.255"));
+    assert!(contains("::/0", "2001:db8::1"));
+    assert!(contains("1.2.3.4/32", "1.2.3.4"));
+    assert!(!contains("1.2.3.4/32", "1.2.3.5"));
+    assert!(contains("2001:db8::1/128", "2001:db8::1"));
+    assert!(!contains("2001:db8::1/128", "2001:db8::2"));
+  }
+
+  #[test]
+  fn matches_blocks() {
+    assert!(contains("10.0.0.0/8", "10.255.0.1"));
+    assert!(!contains("10.0.0.0/8", "11.0.0.1"));
+    // Host bits of the entry don't matter
+    assert!(contains("192.168.1.77/24", "192.168.1.1"));
+    assert!(contains("2001:db8::/32", "2001:db8:ffff::1"));
+    assert!(!contains("2001:db8::/32", "2001:db9::1"));
+    assert!(!contains("10.0.0.0/8", "2001:db8::1"));
+    assert!(!contains("2001:db8::/32", "10.0.0.1"));
+  }
+
+  #[test]
+  fn rejects_bad_entries() {
+    for entry in [
+      "1.2.3.4/33",
+      "2001:db8::/129",
+      "1.2.3.4/",
+      "1.2.3.4/-1",
+      "1.2.3.4/+8",
+      "1.2.3.4/8a",
+      "1.2.3.4/24/8",
+      "1.2.3",
+      "1.2.3.256",
+      "example.com",
+      "",
+    ] {
+      assert!(AddressRange::parse(entry).is_none(), "{:?} was accepted", entry);
+    }
+  }
+
+  #[test]
+  fn normalizes_ipv4_mapped_addresses() {
+    // Clients of a dual stack socket
+    assert!(contains("1.2.3.4", "::ffff:1.2.3.4"));
+    assert!(contains("1.2.3.0/24", "::ffff:1.2.3.99"));
+    assert!(contains("0.0.0.0/0", "::ffff:1.2.3.4"));
+    // Entries written as mapped addresses
+    assert!(contains("::ffff:1.2.3.4", "1.2.3.4"));
+    assert!(contains("::ffff:1.2.3.0/120", "1.2.3.99"));
+    assert!(!contains("::ffff:1.2.3.0/120", "1.2.4.1"));
+    assert!(contains("::ffff:1.2.3.4/128", "::ffff:1.2.3.4"));
+  }
+}
@@ -0,0 +1,186 @@
+use std::{ collections::HashMap, sync::Mutex, time::{ Duration, Instant } };
+use argon2::{ Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString };
+use base64::{ Engine, engine::general_purpose };
+use rand::{ Rng, rngs::OsRng };
+use simplelog::{ error, debug };
+
+use crate::settings::{ WebAuth, WebRole, WebUser };
+
+/// How long a web interface session lasts after logging in.
+pub const SESSION_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Name of the cookie holding the session id.
+pub const SESSION_COOKIE: &str = "crabslumber_session";
+
+/// Who sent a request to the web interface.
+#[derive(Clone, Debug)]
+pub struct Identity {
+  pub name: String,
+  pub role: WebRole,
+}
+
+/// Result of checking the credentials of a request.
+pub enum Authentication {
+  /// No credentials, or an expired session.
+  Anonymous,
+  Authenticated(Identity),
+  /// Wrong credentials, carries who they claimed to be so the attempt can be logged.
+  Failed(String),
+}
+
+struct Session {
+  identity: Identity,
+  expires_at: Instant,
+}
+
+/// Checks the `webAuth` credentials and keeps the sessions of the logged in users.
+pub struct WebAuthenticator {
+  auth: Option<WebAuth>,
+  sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl WebAuthenticator {
+  pub fn new(auth: Option<WebAuth>) -> Self {
+    Self {
+      auth,
+      sessions: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Whether `webAuth` is configured, without it everyone is an anonymous read-only user.
+  pub fn is_enabled(&self) -> bool {
+    self.auth.is_some()
+  }
+
+  /// Checks the `Authorization` header (bearer token or basic username and password), then the session cookie.
+  pub async fn authenticate(&self, authorization: Option<&str>, cookie: Option<&str>) -> Authentication {
+    let auth: &WebAuth = match &self.auth {
+      Some(auth) => auth,
+      None => {
+        return Authentication::Authenticated(Identity { name: String::from("anonymous"), role: WebRole::ReadOnly });
+      }
+    };
+
+    if let Some(authorization) = authorization {
+      let (scheme, credentials) = authorization.trim().split_once(' ').unwrap_or((authorization, ""));
+      let credentials: &str = credentials.trim();
+
+      if scheme.eq_ignore_ascii_case("bearer") {
+        return match auth.tokens.iter().position(|token| constant_time_eq(token.token.as_bytes(), credentials.as_bytes())) {
+          Some(index) => {
+            let identity: Identity = Identity { name: format!("token #{}", index + 1), role: auth.tokens[index].role };
+            Authentication::Authenticated(identity)
+          }
+          None => Authentication::Failed(String::from("bearer token")),
+        };
+      }
+
+      if scheme.eq_ignore_ascii_case("basic") {
+        let decoded: Option<String> = general_purpose::STANDARD.decode(credentials)
+          .ok()
+          .and_then(|decoded| String::from_utf8(decoded).ok());
+        return match decoded.as_deref().and_then(|decoded| decoded.split_once(':')) {
+          Some((username, password)) => {
+            match self.verify_user(username, password).await {
+              Some(identity) => Authentication::Authenticated(identity),
+              None => Authentication::Failed(String::from(username)),
+            }
+          }
+          None => Authentication::Failed(String::from("malformed basic credentials")),
+        };
+      }
+    }
+
+    match cookie.and_then(|cookie| get_cookie(cookie, SESSION_COOKIE)) {
+      Some(session_id) => {
+        match self.get_session(session_id) {
+          Some(identity) => Authentication::Authenticated(identity),
+          None => Authentication::Anonymous,
+        }
+      }
+      None => Authentication::Anonymous,
+    }
+  }
+
+  /// Checks a username and password, opening a session if they match. Returns the session id.
+  pub async fn login(&self, username: &str, password: &str) -> Option<(String, Identity)> {
+    let identity: Identity = self.verify_user(username, password).await?;
+    let session_id: String = new_session_id();
+
+    let mut sessions = self.sessions.lock().unwrap();
+    sessions.retain(|_, session| session.expires_at > Instant::now());
+    sessions.insert(session_id.clone(), Session { identity: identity.clone(), expires_at: Instant::now() + SESSION_DURATION });
+    Some((session_id, identity))
+  }
+
+  /// Closes the session in the cookie, if any.
+  pub fn logout(&self, cookie: Option<&str>) {
+    if let Some(session_id) = cookie.and_then(|cookie| get_cookie(cookie, SESSION_COOKIE)) {
+      self.sessions.lock().unwrap().remove(session_id);
+    }
+  }
+
+  fn get_session(&self, session_id: &str) -> Option<Identity> {
+    let mut sessions = self.sessions.lock().unwrap();
+    match sessions.get(session_id) {
+      Some(session) if session.expires_at > Instant::now() => Some(session.identity.clone()),
+      Some(_) => {
+        sessions.remove(session_id);
+        None
+      }
+      None => None,
+    }
+  }
+
+  async fn verify_user(&self, username: &str, password: &str) -> Option<Identity> {
+    let user: &WebUser = self.auth.as_ref()?.users.iter().find(|user| user.username == username)?;
+    let password_hash: String = user.passwordHash.clone();
+    let password: String = String::from(password);
+
+    // Argon2 is slow on purpose, it would hold up the other requests on this worker
+    let is_valid: bool = tokio::task::spawn_blocking(move || {
+      match PasswordHash::new(&password_hash) {
+        Ok(hash) => Argon2::default().verify_password(password.as_bytes(), &hash).is_ok(),
+        Err(e) => {
+          error!("[Web] Invalid passwordHash in webAuth: {}", e);
+          false
+        }
+      }
+    }).await.unwrap_or(false);
+
+    if is_valid {
+      debug!("[Web] {} logged in", username);
+      Some(Identity { name: user.username.clone(), role: user.role })
+    } else {
+      None
+    }
+  }
+}
+
+/// Hashes a password for the `passwordHash` of a `webAuth` user.
+pub fn hash_password(password: &str) -> Result<String, String> {
+  let salt: SaltString = SaltString::generate(&mut OsRng);
+  Argon2::default()
+    .hash_password(password.as_bytes(), &salt)
+    .map(|hash| hash.to_string())
+    .map_err(|e| e.to_string())
+}
+
+/// Value of a cookie in a `Cookie` header.
+fn get_cookie<'a>(header: &'a str, name: &str) -> Option<&'a str> {
+  header
+    .split(';')
+    .filter_map(|cookie| cookie.trim().split_once('='))
+    .find(|(cookie_name, _)| *cookie_name == name)
+    .map(|(_, value)| value)
+}
+
+fn new_session_id() -> String {
+  let bytes: [u8; 32] = OsRng.gen();
+  bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Compares secrets without leaking where they differ through timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len() && a.iter().zip(b).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
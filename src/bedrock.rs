@@ -0,0 +1,267 @@
+use std::{ collections::HashMap, io, net::SocketAddr, sync::Arc, time::{ Duration, Instant, SystemTime, UNIX_EPOCH } };
+use simplelog::{ error, info, debug, trace };
+use tokio::{ net::UdpSocket, task::JoinHandle };
+use tokio_util::{ sync::CancellationToken, task::TaskTracker };
+
+use crate::access::AccessControl;
+use crate::constants::{ ADDRESS, BEDROCK_VERSION, BEDROCK_PROTOCOL_VERSION };
+use crate::helper::{ display_address, get_motd, MotdOutput, MotdOutputType, Edition, Player, PlayerConnectionCallback };
+use crate::logger::log_fields;
+use crate::metrics::{ BlockReason, METRICS };
+use crate::motd_parser::clean_tags;
+use crate::settings::Settings;
+
+/// Bytes every offline RakNet message carries to tell it apart from garbage.
+const RAKNET_MAGIC: [u8; 16] = [
+  0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+
+const UNCONNECTED_PING_PACKET_ID: u8 = 0x01;
+const UNCONNECTED_PING_OPEN_CONNECTIONS_PACKET_ID: u8 = 0x02;
+const UNCONNECTED_PONG_PACKET_ID: u8 = 0x1c;
+const OPEN_CONNECTION_REQUEST_1_PACKET_ID: u8 = 0x05;
+
+/// Id, ping time, magic and client GUID.
+const UNCONNECTED_PING_LENGTH: usize = 1 + 8 + 16 + 8;
+/// Id, magic and RakNet protocol version, followed by the MTU padding.
+const OPEN_CONNECTION_REQUEST_1_MIN_LENGTH: usize = 1 + 16 + 1;
+
+/// Clients send the connection request again and again while joining, the ones coming from the same address
+/// within this window are the same join attempt.
+const CONNECTION_ATTEMPT_WINDOW: Duration = Duration::from_secs(10);
+
+pub struct SleepingBedrock {
+  settings: Settings,
+  access: Arc<AccessControl>,
+  port: u16,
+  on_player_connection: PlayerConnectionCallback,
+  server_guid: u64,
+  tasks: TaskTracker,
+  closing: CancellationToken,
+  handle: Option<JoinHandle<()>>,
+}
+
+impl SleepingBedrock {
+  /// `shutdown` is the container's token, cancelling it also closes this listener.
+  pub fn new(
+    settings: Settings,
+    access: Arc<AccessControl>,
+    port: u16,
+    on_player_connection: PlayerConnectionCallback,
+    tasks: TaskTracker,
+    shutdown: &CancellationToken
+  ) -> Self {
+    // Only needs to be different between servers the client sees, the time is enough for that
+    let server_guid: u64 = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|time| time.as_nanos() as u64)
+      .unwrap_or(0);
+
+    Self {
+      settings,
+      access,
+      port,
+      on_player_connection,
+      server_guid,
+      tasks,
+      closing: shutdown.child_token(),
+      handle: None,
+    }
+  }
+
+  /// Binds the sleeping listener on `bedrockPort` and answers pings from a background task.
+  pub async fn init(&mut self) -> io::Result<()> {
+    let address: String = format!("{}:{}", ADDRESS, self.port);
+    let socket: UdpSocket = UdpSocket::bind(&address).await?;
+    info!("[Bedrock] Sleeping server listening on {}", address);
+
+    let settings: Settings = self.settings.clone();
+    let access: Arc<AccessControl> = Arc::clone(&self.access);
+    let port: u16 = self.port;
+    let server_guid: u64 = self.server_guid;
+    let on_player_connection: PlayerConnectionCallback = Arc::clone(&self.on_player_connection);
+    let closing: CancellationToken = self.closing.clone();
+
+    self.handle = Some(
+      self.tasks.spawn(async move {
+        let mut buffer: [u8; 2048] = [0; 2048];
+        let mut connection_attempts: HashMap<SocketAddr, Instant> = HashMap::new();
+        loop {
+          tokio::select! {
+            _ = closing.cancelled() => break,
+            received = socket.recv_from(&mut buffer) => {
+              match received {
+                Ok((length, peer)) => {
+                  let datagram: &[u8] = &buffer[..length];
+                  if is_refused(datagram, peer, &settings, &access)
+                    || is_repeated_connection(datagram, peer, &mut connection_attempts) {
+                    continue;
+                  }
+                  let result: io::Result<()> = handle_datagram(
+                    &socket,
+                    datagram,
+                    peer,
+                    &settings,
+                    port,
+                    server_guid,
+                    &on_player_connection
+                  ).await;
+                  if let Err(e) = result {
+                    debug!("[Bedrock] Failed to answer {}: {}", peer, e);
+                  }
+                }
+                Err(e) => error!("[Bedrock] Failed to receive: {}", e),
+              }
+            }
+          }
+        }
+        // The socket is dropped here, releasing the port
+      })
+    );
+
+    Ok(())
+  }
+
+  /// Stops answering and waits until the port is released.
+  pub async fn close(&mut self) {
+    self.closing.cancel();
+    if let Some(handle) = self.handle.take() {
+      if handle.await.is_err() {
+        error!("[Bedrock] Sleeping server task panicked");
+      }
+      info!("[Bedrock] Sleeping server closed");
+    }
+  }
+}
+
+/// Whether the datagram must be ignored: everything from blacklisted or banned addresses, and connection
+/// attempts when a whitelist applies. Only connection attempts are worth a log line.
+fn is_refused(datagram: &[u8], peer: SocketAddr, settings: &Settings, access: &AccessControl) -> bool {
+  let is_connection: bool = datagram.first() == Some(&OPEN_CONNECTION_REQUEST_1_PACKET_ID);
+
+  if let Err(reason) = access.check_address(&peer.ip()) {
+    let address: String = display_address(settings, &peer.ip());
+    if is_connection {
+      log_fields!(
+        info, address = address.as_str(), reason = reason.describe();
+        "[Bedrock] Refused connection from {} address {}", reason.describe(), address
+      );
+      METRICS.blocked_connection(reason);
+    } else {
+      trace!("[Bedrock] Ignored a packet from {} address {}", reason.describe(), address);
+    }
+    return true;
+  }
+
+  // The name only comes after the RakNet handshake, there is nothing to check against the whitelist
+  if is_connection && access.has_whitelist() {
+    let address: String = display_address(settings, &peer.ip());
+    log_fields!(
+      info, address = address.as_str(), reason = BlockReason::Whitelist.describe();
+      "[Bedrock] Bedrock player from {} can't be checked against the whitelist, not waking the server", address
+    );
+    METRICS.blocked_connection(BlockReason::Whitelist);
+    return true;
+  }
+
+  false
+}
+
+/// Whether the datagram is a connection request resent for a join attempt that was already handled, so it wakes,
+/// counts and notifies only once.
+fn is_repeated_connection(
+  datagram: &[u8],
+  peer: SocketAddr,
+  connection_attempts: &mut HashMap<SocketAddr, Instant>
+) -> bool {
+  if datagram.first() != Some(&OPEN_CONNECTION_REQUEST_1_PACKET_ID) {
+    return false;
+  }
+  let now: Instant = Instant::now();
+  connection_attempts.retain(|_, last_request| now.duration_since(*last_request) < CONNECTION_ATTEMPT_WINDOW);
+  if connection_attempts.insert(peer, now).is_some() {
+    trace!("[Bedrock] Repeated connection request from {}", peer);
+    return true;
+  }
+  false
+}
+
+async fn handle_datagram(
+  socket: &UdpSocket,
+  datagram: &[u8],
+  peer: SocketAddr,
+  settings: &Settings,
+  port: u16,
+  server_guid: u64,
+  on_player_connection: &PlayerConnectionCallback
+) -> io::Result<()> {
+  let id: u8 = match datagram.first() {
+    Some(id) => *id,
+    None => {
+      return Ok(());
+    }
+  };
+  trace!("[Bedrock] Received packet 0x{:02x} ({} bytes) from {}", id, datagram.len(), peer);
+
+  match id {
+    UNCONNECTED_PING_PACKET_ID | UNCONNECTED_PING_OPEN_CONNECTIONS_PACKET_ID => {
+      if datagram.len() < UNCONNECTED_PING_LENGTH || datagram[9..25] != RAKNET_MAGIC {
+        return Ok(());
+      }
+      debug!("[Bedrock] Ping from {}", peer);
+      METRICS.status_ping(Edition::Bedrock);
+      let ping_time: &[u8] = &datagram[1..9];
+      socket.send_to(&unconnected_pong(ping_time, settings, port, server_guid), peer).await?;
+      Ok(())
+    }
+    OPEN_CONNECTION_REQUEST_1_PACKET_ID => {
+      if datagram.len() < OPEN_CONNECTION_REQUEST_1_MIN_LENGTH || datagram[1..17] != RAKNET_MAGIC {
+        return Ok(());
+      }
+      // Not answered, the client gives up and retries once the real server is up
+      let address: String = display_address(settings, &peer.ip());
+      log_fields!(
+        info, address = address.as_str();
+        "[Bedrock] Bedrock player from {} is trying to join the server", address
+      );
+      on_player_connection(Player { name: String::from("Bedrock player"), edition: Edition::Bedrock });
+      Ok(())
+    }
+    _ => Ok(()),
+  }
+}
+
+/// Server id string shown in the server list, fields are separated by `;` so they can't contain one.
+fn server_id(settings: &Settings, port: u16, server_guid: u64) -> String {
+  let motd: String = match get_motd(settings, MotdOutputType::PlainText) {
+    Some(MotdOutput::PlainText(motd)) => motd,
+    _ => clean_tags(&settings.serverName),
+  };
+
+  format!(
+    "MCPE;{};{};{};{};{};{};{};{};{};{};{};",
+    motd.replace(';', ""),
+    BEDROCK_PROTOCOL_VERSION,
+    BEDROCK_VERSION,
+    0,
+    settings.maxPlayers,
+    server_guid,
+    env!("CARGO_PKG_NAME"),
+    "Survival",
+    1,
+    port,
+    port
+  )
+}
+
+fn unconnected_pong(ping_time: &[u8], settings: &Settings, port: u16, server_guid: u64) -> Vec<u8> {
+  let server_id: String = server_id(settings, port, server_guid);
+
+  let mut packet: Vec<u8> = vec![UNCONNECTED_PONG_PACKET_ID];
+  packet.extend_from_slice(ping_time);
+  packet.extend_from_slice(&server_guid.to_be_bytes());
+  packet.extend_from_slice(&RAKNET_MAGIC);
+  packet.extend_from_slice(&(server_id.len() as u16).to_be_bytes());
+  packet.extend_from_slice(server_id.as_bytes());
+  packet
+}
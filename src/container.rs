@@ -0,0 +1,812 @@
+use std::{
+  collections::VecDeque,
+  io,
+  process::{ ExitStatus, Stdio },
+  sync::{ Arc, Mutex, MutexGuard, Weak, atomic::{ AtomicBool, Ordering } },
+  time::{ Duration, Instant },
+};
+use simplelog::{ error, info, debug, warn };
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tokio::{
+  io::{ AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader },
+  process::{ Child, ChildStdin, Command },
+  sync::{ broadcast, mpsc::{ self, UnboundedSender }, watch, Notify },
+  task::{ spawn_blocking, JoinHandle },
+  time::{ sleep, timeout },
+};
+use tokio_util::{ sync::CancellationToken, task::TaskTracker };
+
+use crate::access::AccessControl;
+use crate::bedrock::SleepingBedrock;
+use crate::constants::PORT_RELEASE_TIMEOUT;
+use crate::discord::{ Notification, SleepingDiscord };
+use crate::events::{ ServerEvent, EVENT_CAPACITY };
+use crate::helper::{ is_port_listening, is_port_taken, Edition, Player, PlayerConnectionCallback, ServerStatus };
+use crate::java::SleepingJava;
+use crate::logger::{ log_fields, redact_line };
+use crate::metrics::METRICS;
+use crate::settings::Settings;
+use crate::web::SleepingWeb;
+
+/// How often the port and the Minecraft process are checked while waiting on them.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many times the sleeping servers are started again when they fail to bind, waiting twice as long each time.
+const SLEEP_RETRIES: u32 = 5;
+
+/// How long Minecraft gets to save the world after a `stop` on shutdown, before it is killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often the Minecraft lists of `syncMinecraftLists` are checked for changes.
+const LISTS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many lines of Minecraft output are kept for the web consoles that connect later.
+const CONSOLE_SCROLLBACK: usize = 500;
+
+/// Matches the join and leave messages of the server thread, capturing the player name and the action. Vanilla
+/// prefixes them with `[12:34:56] [Server thread/INFO]`, Paper and Spigot with `[12:34:56 INFO]`. Only the start of
+/// the line tells them apart from chat, `/say` or plugin output ending the same way.
+static PLAYER_EVENT_REGEX: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r"^\[(?:[^\]]+\] \[Server thread/INFO|[0-9:]+ INFO)\]: ([A-Za-z0-9_]{3,16}) (joined|left) the game$").unwrap()
+});
+
+/// Minecraft output of every run and the commands sent to it, streamed to the web consoles.
+pub struct Console {
+  scrollback: Mutex<VecDeque<String>>,
+  lines: broadcast::Sender<String>,
+}
+
+impl Console {
+  pub fn new() -> Self {
+    Self {
+      scrollback: Mutex::new(VecDeque::with_capacity(CONSOLE_SCROLLBACK)),
+      lines: broadcast::channel(CONSOLE_SCROLLBACK).0,
+    }
+  }
+
+  /// Adds a line, with its addresses masked like in the logs when `hideIpInLogs` is enabled since read-only
+  /// users see the console too.
+  pub fn push(&self, line: String) {
+    let line: String = redact_line(&line).into_owned();
+    let mut scrollback: MutexGuard<VecDeque<String>> = self.scrollback.lock().unwrap();
+    if scrollback.len() == CONSOLE_SCROLLBACK {
+      scrollback.pop_front();
+    }
+    scrollback.push_back(line.clone());
+    // Nobody listening is fine, the line stays in the scrollback
+    let _ = self.lines.send(line);
+  }
+
+  /// Returns the recent lines and a receiver for the next ones, without missing or repeating any.
+  pub fn subscribe(&self) -> (Vec<String>, broadcast::Receiver<String>) {
+    let scrollback: MutexGuard<VecDeque<String>> = self.scrollback.lock().unwrap();
+    (scrollback.iter().cloned().collect(), self.lines.subscribe())
+  }
+}
+
+impl Default for Console {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Supervisor of the real Minecraft server process. Its output is forwarded line by line to our logger and
+/// console commands can be written to its stdin.
+pub struct MinecraftProcess {
+  pid: Option<u32>,
+  stdin: tokio::sync::Mutex<Option<ChildStdin>>,
+  exit_status: Mutex<Option<ExitStatus>>,
+  exited: watch::Receiver<bool>,
+  kill: Notify,
+  players: Mutex<Vec<String>>,
+  empty_since: Mutex<Option<Instant>>,
+  started_at: Instant,
+  is_stopping: AtomicBool,
+  console: Arc<Console>,
+  events: broadcast::Sender<ServerEvent>,
+}
+
+impl MinecraftProcess {
+  /// Spawns `minecraftCommand` in `minecraftWorkingDirectory` with piped stdio. The process is awaited and its
+  /// output forwarded to our logger and `console` from tasks of `tasks`. Players joining and leaving are sent
+  /// to `events`.
+  pub fn spawn(
+    settings: &Settings,
+    tasks: &TaskTracker,
+    console: Arc<Console>,
+    events: broadcast::Sender<ServerEvent>
+  ) -> io::Result<Arc<Self>> {
+    let mut command_args = settings.minecraftCommand.split_whitespace();
+    let executable: &str = match command_args.next() {
+      Some(executable) => executable,
+      None => {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "minecraftCommand is empty"));
+      }
+    };
+
+    let mut command: Command = Command::new(executable);
+    command.args(command_args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    if let Some(working_directory) = &settings.minecraftWorkingDirectory {
+      command.current_dir(working_directory);
+    }
+
+    let mut child: Child = command.spawn()?;
+    let pid: Option<u32> = child.id();
+    debug!("[Container] Minecraft started with pid {:?}", pid);
+
+    let (exited_sender, exited) = watch::channel(false);
+    let minecraft: Arc<Self> = Arc::new(Self {
+      pid,
+      stdin: tokio::sync::Mutex::new(child.stdin.take()),
+      exit_status: Mutex::new(None),
+      exited,
+      kill: Notify::new(),
+      players: Mutex::new(Vec::new()),
+      empty_since: Mutex::new(Some(Instant::now())),
+      started_at: Instant::now(),
+      is_stopping: AtomicBool::new(false),
+      console,
+      events,
+    });
+
+    if let Some(stdout) = child.stdout.take() {
+      forward_output(tasks, Arc::clone(&minecraft), stdout, false);
+    }
+    if let Some(stderr) = child.stderr.take() {
+      forward_output(tasks, Arc::clone(&minecraft), stderr, true);
+    }
+
+    // The child is owned by this task, everyone else waits on the `exited` channel
+    let waiter: Arc<Self> = Arc::clone(&minecraft);
+    tasks.spawn(async move {
+      let exit_status: io::Result<ExitStatus> = tokio::select! {
+        exit_status = child.wait() => exit_status,
+        _ = waiter.kill.notified() => {
+          match child.start_kill() {
+            Ok(_) => child.wait().await,
+            Err(e) => Err(e),
+          }
+        }
+      };
+
+      match exit_status {
+        Ok(exit_status) => {
+          *waiter.exit_status.lock().unwrap() = Some(exit_status);
+        }
+        Err(e) => error!("[Container] Failed to wait for Minecraft: {}", e),
+      }
+      // Nothing can be written to a dead process
+      waiter.stdin.lock().await.take();
+      let _ = exited_sender.send(true);
+    });
+
+    Ok(minecraft)
+  }
+
+  pub fn id(&self) -> Option<u32> {
+    self.pid
+  }
+
+  /// Writes a console command to the server's stdin.
+  pub async fn send_command(&self, command: &str) -> io::Result<()> {
+    match self.stdin.lock().await.as_mut() {
+      Some(stdin) => {
+        debug!("[Container] Sending command to Minecraft: {}", command);
+        stdin.write_all(format!("{}\n", command.trim_end()).as_bytes()).await?;
+        stdin.flush().await?;
+        self.console.push(format!("> {}", command.trim_end()));
+        Ok(())
+      }
+      None => Err(io::Error::new(io::ErrorKind::BrokenPipe, "Minecraft stdin is closed")),
+    }
+  }
+
+  /// Sends `stop` to the server, which saves the world and exits.
+  pub async fn stop(&self) -> io::Result<()> {
+    self.send_command("stop").await?;
+    self.is_stopping.store(true, Ordering::SeqCst);
+    Ok(())
+  }
+
+  /// Whether `stop` was sent, as opposed to the server being stopped from its own console or crashing.
+  pub fn is_stopping(&self) -> bool {
+    self.is_stopping.load(Ordering::SeqCst)
+  }
+
+  /// How long the process has been running.
+  pub fn uptime(&self) -> Duration {
+    self.started_at.elapsed()
+  }
+
+  /// Waits until the process exits, returning its exit status if it could be read.
+  pub async fn wait(&self) -> Option<ExitStatus> {
+    let mut exited: watch::Receiver<bool> = self.exited.clone();
+    // The sender only goes away once the process has exited
+    let _ = exited.wait_for(|exited| *exited).await;
+    self.exit_status()
+  }
+
+  /// Exit status of the process, `None` while it is still running.
+  pub fn exit_status(&self) -> Option<ExitStatus> {
+    *self.exit_status.lock().unwrap()
+  }
+
+  /// Kills the process, `wait` returns once it is gone.
+  pub fn kill(&self) {
+    self.kill.notify_one();
+  }
+
+  /// Names of the players currently online, according to the server output.
+  pub fn get_players(&self) -> Vec<String> {
+    self.players.lock().unwrap().clone()
+  }
+
+  /// Since when nobody is online, `None` while there are players.
+  pub fn empty_since(&self) -> Option<Instant> {
+    *self.empty_since.lock().unwrap()
+  }
+
+  /// Counts the empty time from now, once the server is up. Nobody could join while it was starting, that time
+  /// doesn't count towards `autoSleepMinutes`.
+  pub fn reset_empty_since(&self) {
+    let mut empty_since: MutexGuard<Option<Instant>> = self.empty_since.lock().unwrap();
+    if empty_since.is_some() {
+      *empty_since = Some(Instant::now());
+    }
+  }
+
+  /// Keeps track of who is online from the "joined the game" and "left the game" lines.
+  fn handle_output_line(&self, line: &str) {
+    let captures: regex::Captures = match PLAYER_EVENT_REGEX.captures(line) {
+      Some(captures) => captures,
+      None => {
+        return;
+      }
+    };
+    let name: &str = &captures[1];
+
+    let mut players: MutexGuard<Vec<String>> = self.players.lock().unwrap();
+    if &captures[2] == "joined" {
+      if !players.iter().any(|player| player == name) {
+        players.push(String::from(name));
+      }
+      let _ = self.events.send(ServerEvent::PlayerJoin { player: String::from(name) });
+    } else {
+      players.retain(|player| player != name);
+      let _ = self.events.send(ServerEvent::PlayerLeave { player: String::from(name) });
+    }
+    debug!("[Container] {} player(s) online: {:?}", players.len(), players);
+
+    let mut empty_since: MutexGuard<Option<Instant>> = self.empty_since.lock().unwrap();
+    if players.is_empty() {
+      empty_since.get_or_insert_with(Instant::now);
+    } else {
+      *empty_since = None;
+    }
+  }
+}
+
+/// Forwards the process output to our logger from a background task, until the pipe is closed.
+fn forward_output<R: AsyncRead + Unpin + Send + 'static>(
+  tasks: &TaskTracker,
+  minecraft: Arc<MinecraftProcess>,
+  output: R,
+  is_stderr: bool
+) {
+  tasks.spawn(async move {
+    let mut lines = BufReader::new(output).lines();
+    loop {
+      match lines.next_line().await {
+        Ok(Some(line)) => {
+          if is_stderr {
+            warn!("[Minecraft] {}", line);
+          } else {
+            info!("[Minecraft] {}", line);
+          }
+          minecraft.handle_output_line(&line);
+          minecraft.console.push(line);
+        }
+        Ok(None) => {
+          break;
+        }
+        Err(e) => {
+          debug!("[Container] Stopped reading Minecraft output: {}", e);
+          break;
+        }
+      }
+    }
+  });
+}
+
+/// Owns the sleeping servers and the real Minecraft server, switching between them.
+///
+/// Everything runs as tasks of `tasks`, `shutdown` is cancelled to stop them all.
+pub struct SleepingContainer {
+  settings: Settings,
+  access: Arc<AccessControl>,
+  status: Mutex<ServerStatus>,
+  java: Mutex<Option<SleepingJava>>,
+  bedrock: Mutex<Option<SleepingBedrock>>,
+  web: Mutex<Option<SleepingWeb>>,
+  minecraft: Mutex<Option<Arc<MinecraftProcess>>>,
+  supervisor: Mutex<Option<JoinHandle<()>>>,
+  console: Arc<Console>,
+  events: broadcast::Sender<ServerEvent>,
+  notifier: Mutex<Option<UnboundedSender<Notification>>>,
+  shutdown: CancellationToken,
+  tasks: TaskTracker,
+}
+
+impl SleepingContainer {
+  /// Must be called from within the runtime, the Discord notifier is started right away.
+  pub fn new(settings: Settings) -> Arc<Self> {
+    let tasks: TaskTracker = TaskTracker::new();
+
+    let notifier: Option<UnboundedSender<Notification>> = settings.discordWebhookUrl.as_ref().map(|_| {
+      let (sender, receiver) = mpsc::unbounded_channel();
+      tasks.spawn(SleepingDiscord::new(settings.clone()).run(receiver));
+      sender
+    });
+
+    let container: Arc<Self> = Arc::new(Self {
+      access: Arc::new(AccessControl::new(&settings)),
+      settings,
+      status: Mutex::new(ServerStatus::Sleeping),
+      java: Mutex::new(None),
+      bedrock: Mutex::new(None),
+      web: Mutex::new(None),
+      minecraft: Mutex::new(None),
+      supervisor: Mutex::new(None),
+      console: Arc::new(Console::new()),
+      events: broadcast::channel(EVENT_CAPACITY).0,
+      notifier: Mutex::new(notifier),
+      shutdown: CancellationToken::new(),
+      tasks,
+    });
+    if container.access.syncs_minecraft_lists() {
+      container.watch_minecraft_lists();
+    }
+    container
+  }
+
+  /// Reloads the Minecraft lists from a background task whenever they change, until the shutdown.
+  fn watch_minecraft_lists(&self) {
+    let access: Arc<AccessControl> = Arc::clone(&self.access);
+    let events: broadcast::Sender<ServerEvent> = self.events.clone();
+    let shutdown: CancellationToken = self.shutdown.clone();
+
+    self.tasks.spawn(async move {
+      loop {
+        tokio::select! {
+          _ = shutdown.cancelled() => break,
+          _ = sleep(LISTS_POLL_INTERVAL) => (),
+        }
+        // The lists are read with std::fs, which would hold up the other tasks of this worker
+        let lists: Arc<AccessControl> = Arc::clone(&access);
+        let reload: JoinHandle<Vec<&'static str>> = spawn_blocking(move || lists.reload_minecraft_lists());
+        let changed: Vec<&'static str> = match reload.await {
+          Ok(changed) => changed,
+          Err(e) => {
+            error!("[Container] Failed to reload the Minecraft lists: {}", e);
+            continue;
+          }
+        };
+        for file in changed {
+          info!("[Container] Reloaded {}", file);
+          let _ = events.send(ServerEvent::SettingsReload { file: String::from(file) });
+        }
+      }
+    });
+  }
+
+  pub fn get_status(&self) -> ServerStatus {
+    *self.status.lock().unwrap()
+  }
+
+  /// Handle on the Minecraft process, if it is running.
+  pub fn get_minecraft(&self) -> Option<Arc<MinecraftProcess>> {
+    self.minecraft.lock().unwrap().clone()
+  }
+
+  /// Who may reach the sleeping servers.
+  pub fn get_access(&self) -> Arc<AccessControl> {
+    Arc::clone(&self.access)
+  }
+
+  /// Output of the Minecraft server, kept between runs.
+  pub fn get_console(&self) -> Arc<Console> {
+    Arc::clone(&self.console)
+  }
+
+  /// Receives the events from now on.
+  pub fn subscribe_events(&self) -> broadcast::Receiver<ServerEvent> {
+    self.events.subscribe()
+  }
+
+  /// Sends an event to the `/api/events` streams, if anyone is listening.
+  fn emit(&self, event: ServerEvent) {
+    let _ = self.events.send(event);
+  }
+
+  /// Writes a console command to the Minecraft server.
+  pub async fn send_command(&self, command: &str) -> io::Result<()> {
+    match self.get_minecraft() {
+      Some(minecraft) => minecraft.send_command(command).await,
+      None => Err(io::Error::new(io::ErrorKind::NotConnected, "Minecraft is not running")),
+    }
+  }
+
+  fn set_status(&self, status: ServerStatus) {
+    let mut current: MutexGuard<ServerStatus> = self.status.lock().unwrap();
+    if *current != status {
+      log_fields!(
+        info, status = format!("{:?}", status), previous_status = format!("{:?}", *current);
+        "[Container] Server status: {:?} -> {:?}", *current, status
+      );
+      self.emit(ServerEvent::StatusChange { from: *current, to: status });
+      METRICS.set_status(status);
+      *current = status;
+    }
+  }
+
+  /// Starts the sleeping servers, waiting for a player to wake the real one.
+  pub async fn init(self: &Arc<Self>) -> io::Result<()> {
+    let container: Weak<Self> = Arc::downgrade(self);
+    let on_player_connection: PlayerConnectionCallback = Arc::new(move |player: Player| {
+      if let Some(container) = container.upgrade() {
+        container.wake_up(player);
+      }
+    });
+
+    let mut java: SleepingJava = SleepingJava::new(
+      self.settings.clone(),
+      Arc::clone(&self.access),
+      Arc::clone(&on_player_connection),
+      self.tasks.clone(),
+      &self.shutdown
+    );
+    java.init().await?;
+    *self.java.lock().unwrap() = Some(java);
+
+    if let Some(bedrock_port) = self.settings.bedrockPort {
+      let mut bedrock: SleepingBedrock = SleepingBedrock::new(
+        self.settings.clone(),
+        Arc::clone(&self.access),
+        bedrock_port,
+        on_player_connection,
+        self.tasks.clone(),
+        &self.shutdown
+      );
+      bedrock.init().await?;
+      *self.bedrock.lock().unwrap() = Some(bedrock);
+    }
+
+    // The web interface stays up while Minecraft runs, unless webStopOnStart closed it
+    let is_web_running: bool = self.web.lock().unwrap().is_some();
+    if self.settings.webPort != 0 && !is_web_running {
+      let mut web: SleepingWeb = SleepingWeb::new(
+        self.settings.clone(),
+        Arc::downgrade(self),
+        self.tasks.clone(),
+        &self.shutdown
+      );
+      match web.init().await {
+        Ok(_) => {
+          *self.web.lock().unwrap() = Some(web);
+        }
+        Err(e) => error!("[Web] Failed to start the web interface: {}", e),
+      }
+    }
+
+    self.set_status(ServerStatus::Sleeping);
+    Ok(())
+  }
+
+  /// Closes the sleeping servers, releasing their ports.
+  pub async fn close(&self) {
+    let java: Option<SleepingJava> = self.java.lock().unwrap().take();
+    if let Some(mut java) = java {
+      java.close().await;
+    }
+    let bedrock: Option<SleepingBedrock> = self.bedrock.lock().unwrap().take();
+    if let Some(mut bedrock) = bedrock {
+      bedrock.close().await;
+    }
+  }
+
+  /// Stops Minecraft, the server goes back to sleep once it has exited.
+  pub async fn stop_minecraft(&self) -> io::Result<()> {
+    match self.get_minecraft() {
+      Some(minecraft) => minecraft.stop().await,
+      None => Err(io::Error::new(io::ErrorKind::NotConnected, "Minecraft is not running")),
+    }
+  }
+
+  /// Closes the web interface, releasing its port.
+  async fn close_web(&self) {
+    let web: Option<SleepingWeb> = self.web.lock().unwrap().take();
+    if let Some(mut web) = web {
+      web.close().await;
+    }
+  }
+
+  /// Asks everything to stop, `wait_for_shutdown` does the actual work.
+  pub fn request_shutdown(&self) {
+    self.shutdown.cancel();
+  }
+
+  /// Waits until a shutdown is requested, then stops Minecraft, the sleeping servers and every other task.
+  /// Pending Discord notifications are sent before this returns.
+  pub async fn wait_for_shutdown(&self) {
+    self.shutdown.cancelled().await;
+    info!("[Container] Shutting down");
+
+    self.close().await;
+    self.close_web().await;
+    // The supervisor stops Minecraft by itself when the shutdown is requested
+    let supervisor: Option<JoinHandle<()>> = self.supervisor.lock().unwrap().take();
+    if let Some(supervisor) = supervisor {
+      if supervisor.await.is_err() {
+        error!("[Container] Minecraft supervisor panicked");
+      }
+    }
+
+    // The notifier ends once its queue is empty and nobody can add to it anymore
+    self.notifier.lock().unwrap().take();
+    self.tasks.close();
+    self.tasks.wait().await;
+  }
+
+  /// Wakes the real server up, called by the sleeping servers and the web interface.
+  /// Returns `false` if the server was not sleeping.
+  pub fn wake_up(self: &Arc<Self>, player: Player) -> bool {
+    METRICS.wake_attempt(player.edition);
+    let accepted: bool = self.try_start(&player);
+    self.emit(ServerEvent::WakeAttempt { player: player.name.clone(), edition: player.edition, accepted });
+    if !accepted {
+      return false;
+    }
+    let who: String = match player.edition {
+      Edition::Java => format!("Player {}", player.name),
+      Edition::Bedrock | Edition::Web => player.name.clone(),
+    };
+    log_fields!(
+      info, player = player.name.as_str(), edition = format!("{:?}", player.edition);
+      "[Container] {} woke the server", who
+    );
+    self.notify_discord(Notification::PlayerLogging(player.name));
+
+    let container: Arc<Self> = Arc::clone(self);
+    let supervisor: JoinHandle<()> = self.tasks.spawn(async move {
+      container.close().await;
+      if container.settings.webStopOnStart {
+        container.close_web().await;
+      }
+
+      if !container.settings.startMinecraft {
+        info!("[Container] startMinecraft is disabled, closing so the server can be started by someone else");
+        container.request_shutdown();
+        return;
+      }
+
+      container.start_minecraft().await;
+    });
+    *self.supervisor.lock().unwrap() = Some(supervisor);
+    true
+  }
+
+  /// Switches from sleeping to starting, returns `false` if the server was not sleeping or is shutting down.
+  fn try_start(&self, player: &Player) -> bool {
+    if self.shutdown.is_cancelled() {
+      return false;
+    }
+    let mut status: MutexGuard<ServerStatus> = self.status.lock().unwrap();
+    if *status != ServerStatus::Sleeping {
+      debug!("[Container] Player {} tried to join while the server is {:?}", player.name, *status);
+      return false;
+    }
+    log_fields!(
+      info, status = format!("{:?}", ServerStatus::Starting), previous_status = format!("{:?}", *status);
+      "[Container] Server status: {:?} -> {:?}", *status, ServerStatus::Starting
+    );
+    self.emit(ServerEvent::StatusChange { from: *status, to: ServerStatus::Starting });
+    METRICS.set_status(ServerStatus::Starting);
+    *status = ServerStatus::Starting;
+    true
+  }
+
+  /// Queues a Discord notification, if the webhook is configured.
+  fn notify_discord(&self, notification: Notification) {
+    if let Some(notifier) = self.notifier.lock().unwrap().as_ref() {
+      let _ = notifier.send(notification);
+    }
+  }
+
+  /// Goes back to sleep after the Minecraft server could not be started or has stopped.
+  async fn back_to_sleep(self: &Arc<Self>) {
+    if self.shutdown.is_cancelled() {
+      return;
+    }
+    let mut delay: Duration = POLL_INTERVAL;
+    let mut attempt: u32 = 1;
+    loop {
+      let error: io::Error = match self.init().await {
+        Ok(_) => {
+          return;
+        }
+        Err(e) => e,
+      };
+      // Whatever did bind would make the next attempt fail
+      self.close().await;
+      if attempt == SLEEP_RETRIES {
+        error!("[Container] Failed to restart the sleeping servers: {}", error);
+        break;
+      }
+      warn!("[Container] Failed to restart the sleeping servers, retrying in {:?}: {}", delay, error);
+      tokio::select! {
+        _ = self.shutdown.cancelled() => {
+          return;
+        }
+        _ = sleep(delay) => (),
+      }
+      delay *= 2;
+      attempt += 1;
+    }
+
+    // Nobody could wake the server anymore, staying up would only hide it
+    error!("[Container] Nothing is listening for players anymore, shutting down");
+    self.set_status(ServerStatus::Stopped);
+    self.request_shutdown();
+  }
+
+  async fn start_minecraft(self: &Arc<Self>) {
+    if !wait_for_port_release(self.settings.serverPort, &self.shutdown).await {
+      if !self.shutdown.is_cancelled() {
+        error!("[Container] Port {} is still taken, not starting Minecraft", self.settings.serverPort);
+        self.back_to_sleep().await;
+      }
+      return;
+    }
+
+    info!("----------- Starting Minecraft : {} -----------", self.settings.minecraftCommand);
+    let minecraft: Arc<MinecraftProcess> = match MinecraftProcess::spawn(
+      &self.settings,
+      &self.tasks,
+      Arc::clone(&self.console),
+      self.events.clone()
+    ) {
+      Ok(minecraft) => minecraft,
+      Err(e) => {
+        error!("[Container] Failed to start Minecraft: {}", e);
+        self.back_to_sleep().await;
+        return;
+      }
+    };
+    *self.minecraft.lock().unwrap() = Some(Arc::clone(&minecraft));
+
+    let auto_sleep: Option<Duration> = self.settings.autoSleepMinutes
+      .filter(|minutes| *minutes > 0)
+      .map(|minutes| Duration::from_secs((minutes as u64) * 60));
+    let mut is_shutting_down: bool = false;
+
+    let exit_status: Option<ExitStatus> = loop {
+      tokio::select! {
+        exit_status = minecraft.wait() => {
+          break exit_status;
+        }
+        _ = self.shutdown.cancelled(), if !is_shutting_down => {
+          is_shutting_down = true;
+          info!("[Container] Stopping Minecraft before exiting");
+          if let Err(e) = minecraft.stop().await {
+            error!("[Container] Failed to stop Minecraft: {}", e);
+          }
+          if timeout(STOP_TIMEOUT, minecraft.wait()).await.is_err() {
+            warn!("[Container] Minecraft did not stop after {} seconds, killing it", STOP_TIMEOUT.as_secs());
+            minecraft.kill();
+          }
+        }
+        _ = sleep(POLL_INTERVAL) => {
+          if self.get_status() == ServerStatus::Starting && is_port_listening(self.settings.serverPort).await {
+            minecraft.reset_empty_since();
+            self.set_status(ServerStatus::Running);
+          }
+
+          if let (Some(auto_sleep), Some(empty_since)) = (auto_sleep, minecraft.empty_since()) {
+            if !minecraft.is_stopping() && self.get_status() == ServerStatus::Running && empty_since.elapsed() >= auto_sleep {
+              info!("[Container] Nobody was online for {} minute(s), putting the server to sleep", auto_sleep.as_secs() / 60);
+              if let Err(e) = minecraft.stop().await {
+                error!("[Container] Failed to stop Minecraft: {}", e);
+              }
+            }
+          }
+        }
+      }
+    };
+    self.minecraft.lock().unwrap().take();
+
+    match exit_status {
+      Some(exit_status) => info!("----------- Minecraft stopped {} -----------", exit_status),
+      None => info!("----------- Minecraft stopped -----------"),
+    }
+    self.notify_discord(Notification::ServerStop);
+    if !minecraft.is_stopping() && !exit_status.is_some_and(|exit_status| exit_status.success()) {
+      self.emit(ServerEvent::Crash { code: exit_status.and_then(|exit_status| exit_status.code()) });
+    }
+
+    if self.shutdown.is_cancelled() {
+      self.set_status(ServerStatus::Stopped);
+      return;
+    }
+    // Stopping because the server was empty or through the API is what sleeping is about, otherwise it was
+    // stopped by someone from the console or crashed
+    if minecraft.is_stopping() || self.settings.preventStop.unwrap_or(false) {
+      self.back_to_sleep().await;
+    } else {
+      info!("[Container] Minecraft was stopped and preventStop is disabled, exiting");
+      self.set_status(ServerStatus::Stopped);
+      self.request_shutdown();
+    }
+  }
+}
+
+/// Waits for whatever is using the port (usually the last Minecraft run) to release it.
+/// Gives up on timeout or when a shutdown is requested.
+async fn wait_for_port_release(port: u16, shutdown: &CancellationToken) -> bool {
+  let started: Instant = Instant::now();
+  while is_port_taken(port) {
+    if started.elapsed() >= Duration::from_millis(PORT_RELEASE_TIMEOUT as u64) {
+      return false;
+    }
+    warn!("[Container] Waiting for port {} to be released", port);
+    tokio::select! {
+      _ = shutdown.cancelled() => {
+        return false;
+      }
+      _ = sleep(POLL_INTERVAL) => (),
+    }
+  }
+  !shutdown.is_cancelled()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::PLAYER_EVENT_REGEX;
+
+  fn player_event(line: &str) -> Option<(String, String)> {
+    PLAYER_EVENT_REGEX.captures(line).map(|captures| (String::from(&captures[1]), String::from(&captures[2])))
+  }
+
+  #[test]
+  fn matches_server_join_and_leave() {
+    assert_eq!(
+      player_event("[12:34:56] [Server thread/INFO]: Steve joined the game"),
+      Some((String::from("Steve"), String::from("joined")))
+    );
+    assert_eq!(
+      player_event("[12:34:56] [Server thread/INFO]: Alex_01 left the game"),
+      Some((String::from("Alex_01"), String::from("left")))
+    );
+    assert_eq!(
+      player_event("[12:34:56 INFO]: Steve joined the game"),
+      Some((String::from("Steve"), String::from("joined")))
+    );
+  }
+
+  #[test]
+  fn ignores_spoofed_lines() {
+    // Chat, /say and /me
+    assert_eq!(player_event("[12:34:56] [Server thread/INFO]: <Steve> Alex left the game"), None);
+    assert_eq!(player_event("[12:34:56] [Async Chat Thread - #0/INFO]: <Steve> Alex left the game"), None);
+    assert_eq!(player_event("[12:34:56 INFO]: <Steve> Alex left the game"), None);
+    assert_eq!(player_event("[12:34:56] [Server thread/INFO]: [Steve] Alex left the game"), None);
+    assert_eq!(player_event("[12:34:56] [Server thread/INFO]: * Steve Alex left the game"), None);
+    // Plugin output and other threads
+    assert_eq!(player_event("[12:34:56] [Server thread/INFO]: [Essentials] Alex left the game"), None);
+    assert_eq!(player_event("[12:34:56] [User Authenticator #1/INFO]: Alex left the game"), None);
+    assert_eq!(player_event("[12:34:56] [Server thread/WARN]: Alex left the game"), None);
+    // Not a valid player name
+    assert_eq!(player_event("[12:34:56] [Server thread/INFO]: Al left the game"), None);
+    assert_eq!(player_event("[12:34:56] [Server thread/INFO]: Alex-01 left the game"), None);
+  }
+}
@@ -1,7 +1,9 @@
 use simplelog::{ error, info };
 use serde::{ Serialize };
 use reqwest::Client;
+use tokio::sync::mpsc::UnboundedReceiver;
 
+use crate::metrics::METRICS;
 use crate::settings::Settings;
 
 #[derive(Serialize)]
@@ -18,6 +20,12 @@ struct DiscordContent {
   avatar_url: String,
 }
 
+/// Messages the container asks to send, in the order they happened.
+pub enum Notification {
+  PlayerLogging(String),
+  ServerStop,
+}
+
 pub struct SleepingDiscord {
   settings: Settings,
   client: Client,
@@ -47,6 +55,9 @@ impl SleepingDiscord {
         .send().await;
 
       if let Ok(response) = response {
+        if !response.status().is_success() {
+          METRICS.discord_failure();
+        }
         if let Ok(text) = response.text().await {
           info!("[Discord] response: {}", text);
         }
@@ -55,6 +66,7 @@ impl SleepingDiscord {
         }
       }
       else if let Err(err) = response {
+        METRICS.discord_failure();
         error!("[Discord] Failed to send message: {}", err);
       }
     }
@@ -87,4 +99,14 @@ impl SleepingDiscord {
 
     self.send_message(content, false).await;
   }
+
+  /// Sends notifications one after the other until every sender is dropped, so none is lost on shutdown.
+  pub async fn run(self, mut notifications: UnboundedReceiver<Notification>) {
+    while let Some(notification) = notifications.recv().await {
+      match notification {
+        Notification::PlayerLogging(player_name) => self.on_player_logging(&player_name).await,
+        Notification::ServerStop => self.on_server_stop().await,
+      }
+    }
+  }
 }
@@ -0,0 +1,50 @@
+use serde::Serialize;
+
+use crate::helper::{ Edition, ServerStatus };
+
+/// How many events a slow `/api/events` client can fall behind before it misses some.
+pub const EVENT_CAPACITY: usize = 100;
+
+/// Something that happened to the server, streamed by `GET /api/events`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ServerEvent {
+  StatusChange {
+    from: ServerStatus,
+    to: ServerStatus,
+  },
+  /// Someone tried to wake the server, `accepted` is `false` if it was not sleeping.
+  WakeAttempt {
+    player: String,
+    edition: Edition,
+    accepted: bool,
+  },
+  PlayerJoin {
+    player: String,
+  },
+  PlayerLeave {
+    player: String,
+  },
+  /// Minecraft exited on its own with an error, `code` is `None` if it was killed by a signal.
+  Crash {
+    code: Option<i32>,
+  },
+  /// A settings file changed and was read again, such as the Minecraft lists of `syncMinecraftLists`.
+  SettingsReload {
+    file: String,
+  },
+}
+
+impl ServerEvent {
+  /// Name of the event in the stream, the same as its `type`.
+  pub fn name(&self) -> &'static str {
+    match self {
+      ServerEvent::StatusChange { .. } => "statusChange",
+      ServerEvent::WakeAttempt { .. } => "wakeAttempt",
+      ServerEvent::PlayerJoin { .. } => "playerJoin",
+      ServerEvent::PlayerLeave { .. } => "playerLeave",
+      ServerEvent::Crash { .. } => "crash",
+      ServerEvent::SettingsReload { .. } => "settingsReload",
+    }
+  }
+}
@@ -1,11 +1,23 @@
-use std::{ net::TcpListener, fs::File, io::{ BufReader, Read }, path::{ Path }, env };
+use std::{ net::{ IpAddr, TcpListener }, fs::File, io::{ BufReader, Read }, path::{ Path }, env, sync::Arc, time::Duration };
+use tokio::{ net::TcpStream, time::timeout };
 use base64::{ Engine, engine::general_purpose };
 use simplelog::{ error, debug, trace };
+use serde::Serialize;
 use serde_json::{ Value };
 
 use crate::motd_parser::{ clean_tags, text_to_json, text_to_html };
 use crate::constants::DEFAULT_FAV_ICON_STRING;
-use crate::settings::Settings;
+use crate::logger::redact_address;
+use crate::settings::{ IpRedaction, Settings };
+
+/// How long `is_port_listening` waits for the connection.
+const PORT_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Whether something accepts connections on the port. Unlike `is_port_taken` it never holds the port, so it
+/// can't get in the way of a server about to bind it.
+pub async fn is_port_listening(port: u16) -> bool {
+  matches!(timeout(PORT_PROBE_TIMEOUT, TcpStream::connect(("127.0.0.1", port))).await, Ok(Ok(_)))
+}
 
 pub fn is_port_taken(port: u16) -> bool {
   match TcpListener::bind(format!("127.0.0.1:{}", port)) {
@@ -21,13 +33,26 @@ pub fn is_port_taken(port: u16) -> bool {
   }
 }
 
-pub fn is_in_dev() -> bool {
-  match env::var("ENV") {
-    Ok(_) => true,
-    Err(_) => false,
+/// Address of a client as it should appear in the logs, masked according to `ipRedaction` when `hideIpInLogs`
+/// is enabled. The logger masks whatever is left over, this only saves it the search.
+pub fn display_address(settings: &Settings, address: &IpAddr) -> String {
+  match get_ip_redaction(settings) {
+    Some(redaction) => redact_address(address, redaction),
+    None => address.to_string(),
   }
 }
 
+/// Masking of the addresses in the logs, `None` unless `hideIpInLogs` is enabled.
+pub fn get_ip_redaction(settings: &Settings) -> Option<IpRedaction> {
+  settings.hideIpInLogs
+    .unwrap_or(false)
+    .then_some(settings.ipRedaction.unwrap_or(IpRedaction::Full))
+}
+
+pub fn is_in_dev() -> bool {
+  env::var("ENV").is_ok()
+}
+
 fn make_absolute_path(path: &String) -> String {
   let path = Path::new(&path);
 
@@ -53,24 +78,24 @@ pub fn get_fav_icon(settings: &Settings) -> String {
         let mut buffer: Vec<u8> = Vec::new();
         match reader.read_to_end(&mut buffer) {
           Ok(_) => {
-            let base64: String = general_purpose::URL_SAFE.encode(&buffer);
+            let base64: String = general_purpose::STANDARD.encode(&buffer);
             let favicon: String = format!("data:image/png;base64,{}", base64);
             trace!("FavIcon base64: {}", favicon);
             return favicon;
           }
           Err(e) => {
             error!("Failed to read favIconPath: {}", e);
-            return String::from(DEFAULT_FAV_ICON_STRING.clone());
+            return String::from(DEFAULT_FAV_ICON_STRING);
           }
         }
       }
       Err(e) => {
         error!("Failed to open favIconPath: {}", e);
-        return String::from(DEFAULT_FAV_ICON_STRING.clone());
+        return String::from(DEFAULT_FAV_ICON_STRING);
       }
     }
   }
-  return String::from(DEFAULT_FAV_ICON_STRING.clone());
+  String::from(DEFAULT_FAV_ICON_STRING)
 }
 
 pub enum MotdOutputType {
@@ -94,9 +119,28 @@ pub fn get_motd(settings: &Settings, output_type: MotdOutputType) -> Option<Motd
   }
 }
 
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ServerStatus {
   Sleeping,
   Running,
   Starting,
   Stopped,
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Edition {
+  Java,
+  Bedrock,
+  /// Not a player, someone who pressed "Wake up" on the web interface.
+  Web,
+}
+
+#[derive(Clone, Debug)]
+pub struct Player {
+  /// Player name, Bedrock players are only known as "Bedrock player" since the name comes after RakNet.
+  pub name: String,
+  pub edition: Edition,
+}
+
+/// Called by the sleeping servers when a player tries to join, which is what wakes the real server.
+pub type PlayerConnectionCallback = Arc<dyn Fn(Player) + Send + Sync>;
@@ -0,0 +1,439 @@
+use std::{ io::{ self, Cursor }, sync::Arc, time::Duration };
+use simplelog::{ error, info, debug, warn, trace };
+use serde_json::{ Value, json };
+use tokio::{
+  io::{ AsyncRead, AsyncReadExt, AsyncWriteExt },
+  net::{ TcpListener, TcpStream },
+  task::JoinHandle,
+  time::timeout,
+};
+use tokio_util::{ sync::CancellationToken, task::TaskTracker };
+
+use crate::access::{ AccessControl, PlayerRefusal };
+use crate::constants::{ ADDRESS, MC_TIMEOUT, LATEST_MINECRAFT_VERSION, LEGACY_PROTOCOL_VERSION };
+use crate::helper::{
+  display_address,
+  get_motd,
+  get_fav_icon,
+  MotdOutput,
+  MotdOutputType,
+  Edition,
+  Player,
+  PlayerConnectionCallback,
+};
+use crate::logger::log_fields;
+use crate::metrics::{ BlockReason, METRICS };
+use crate::motd_parser::{ clean_tags, text_to_json };
+use crate::settings::{ Settings, Version };
+use crate::versions::{ get_protocol, get_version_name, describe_protocol };
+
+/// Largest packet a client is allowed to send us (biggest 3 bytes VarInt), same limit as vanilla.
+const MAX_PACKET_LENGTH: i32 = 2097151;
+
+const HANDSHAKE_PACKET_ID: i32 = 0x00;
+const STATUS_REQUEST_PACKET_ID: i32 = 0x00;
+const STATUS_RESPONSE_PACKET_ID: i32 = 0x00;
+const PING_PACKET_ID: i32 = 0x01;
+const PONG_PACKET_ID: i32 = 0x01;
+const LOGIN_START_PACKET_ID: i32 = 0x00;
+const LOGIN_DISCONNECT_PACKET_ID: i32 = 0x00;
+
+/// First byte sent by pre-1.7 clients for the server list ping, it can never start a modern packet.
+const LEGACY_PING_PACKET_ID: u8 = 0xfe;
+const LEGACY_KICK_PACKET_ID: u8 = 0xff;
+
+const NEXT_STATE_STATUS: i32 = 1;
+const NEXT_STATE_LOGIN: i32 = 2;
+
+/// Disconnect message for the players missing from `whiteListedNames`, unless `whiteListMessage` is set.
+const NOT_WHITELISTED_MESSAGE: &str = "You are not whitelisted on this server";
+
+fn invalid_data(message: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn timed_out() -> io::Error {
+  io::Error::new(io::ErrorKind::TimedOut, "Client took too long to answer")
+}
+
+async fn read_var_int<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<i32> {
+  let mut value: i32 = 0;
+  let mut buffer: [u8; 1] = [0];
+
+  for position in 0..5 {
+    reader.read_exact(&mut buffer).await?;
+    value |= ((buffer[0] & 0x7f) as i32) << (position * 7);
+    if buffer[0] & 0x80 == 0 {
+      return Ok(value);
+    }
+  }
+
+  Err(invalid_data("VarInt is too big"))
+}
+
+fn write_var_int(buffer: &mut Vec<u8>, value: i32) {
+  let mut value: u32 = value as u32;
+  loop {
+    if value & !0x7f == 0 {
+      buffer.push(value as u8);
+      return;
+    }
+    buffer.push(((value & 0x7f) | 0x80) as u8);
+    value >>= 7;
+  }
+}
+
+async fn read_string<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<String> {
+  let length: i32 = read_var_int(reader).await?;
+  if !(0..=MAX_PACKET_LENGTH).contains(&length) {
+    return Err(invalid_data("String length is out of bounds"));
+  }
+  let mut buffer: Vec<u8> = vec![0; length as usize];
+  reader.read_exact(&mut buffer).await?;
+  String::from_utf8(buffer).map_err(|_| invalid_data("String is not valid UTF-8"))
+}
+
+fn write_string(buffer: &mut Vec<u8>, value: &str) {
+  write_var_int(buffer, value.len() as i32);
+  buffer.extend_from_slice(value.as_bytes());
+}
+
+/// Reads a whole uncompressed packet, returning its id and a cursor over the remaining data.
+/// Gives up if the client doesn't send it within `MC_TIMEOUT`.
+async fn read_packet(stream: &mut TcpStream) -> io::Result<(i32, Cursor<Vec<u8>>)> {
+  let buffer: Vec<u8> = timeout(client_timeout(), async {
+    let length: i32 = read_var_int(stream).await?;
+    if !(1..=MAX_PACKET_LENGTH).contains(&length) {
+      return Err(invalid_data("Packet length is out of bounds"));
+    }
+    let mut buffer: Vec<u8> = vec![0; length as usize];
+    stream.read_exact(&mut buffer).await?;
+    Ok(buffer)
+  }).await.map_err(|_| timed_out())??;
+  let length: usize = buffer.len();
+
+  let mut data: Cursor<Vec<u8>> = Cursor::new(buffer);
+  let id: i32 = read_var_int(&mut data).await?;
+  trace!("[Java] Received packet 0x{:02x} ({} bytes)", id, length);
+  Ok((id, data))
+}
+
+async fn send_packet(stream: &mut TcpStream, id: i32, data: &[u8]) -> io::Result<()> {
+  let mut body: Vec<u8> = Vec::new();
+  write_var_int(&mut body, id);
+  body.extend_from_slice(data);
+
+  let mut packet: Vec<u8> = Vec::new();
+  write_var_int(&mut packet, body.len() as i32);
+  packet.extend_from_slice(&body);
+
+  stream.write_all(&packet).await?;
+  stream.flush().await
+}
+
+struct Handshake {
+  protocol_version: i32,
+  server_address: String,
+  server_port: u16,
+  next_state: i32,
+}
+
+impl Handshake {
+  async fn read<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Self> {
+    let protocol_version: i32 = read_var_int(reader).await?;
+    let server_address: String = read_string(reader).await?;
+    let mut port: [u8; 2] = [0; 2];
+    reader.read_exact(&mut port).await?;
+    let next_state: i32 = read_var_int(reader).await?;
+
+    Ok(Self {
+      protocol_version,
+      server_address,
+      server_port: u16::from_be_bytes(port),
+      next_state,
+    })
+  }
+}
+
+pub struct SleepingJava {
+  settings: Settings,
+  access: Arc<AccessControl>,
+  on_player_connection: PlayerConnectionCallback,
+  tasks: TaskTracker,
+  closing: CancellationToken,
+  handle: Option<JoinHandle<()>>,
+}
+
+impl SleepingJava {
+  /// `shutdown` is the container's token, cancelling it also closes this listener.
+  pub fn new(
+    settings: Settings,
+    access: Arc<AccessControl>,
+    on_player_connection: PlayerConnectionCallback,
+    tasks: TaskTracker,
+    shutdown: &CancellationToken
+  ) -> Self {
+    Self {
+      settings,
+      access,
+      on_player_connection,
+      tasks,
+      closing: shutdown.child_token(),
+      handle: None,
+    }
+  }
+
+  /// Binds the sleeping listener on `serverPort` and serves clients from a background task.
+  pub async fn init(&mut self) -> io::Result<()> {
+    let address: String = format!("{}:{}", ADDRESS, self.settings.serverPort);
+    let listener: TcpListener = TcpListener::bind(&address).await?;
+    info!("[Java] Sleeping server listening on {}", address);
+
+    let settings: Settings = self.settings.clone();
+    let access: Arc<AccessControl> = Arc::clone(&self.access);
+    let on_player_connection: PlayerConnectionCallback = Arc::clone(&self.on_player_connection);
+    let closing: CancellationToken = self.closing.clone();
+    let tasks: TaskTracker = self.tasks.clone();
+
+    self.handle = Some(
+      self.tasks.spawn(async move {
+        loop {
+          tokio::select! {
+            _ = closing.cancelled() => break,
+            accepted = listener.accept() => {
+              match accepted {
+                Ok((stream, peer)) => {
+                  if let Err(reason) = access.check_address(&peer.ip()) {
+                    let address: String = display_address(&settings, &peer.ip());
+                    log_fields!(
+                      info, address = address.as_str(), reason = reason.describe();
+                      "[Java] Refused connection from {} address {}", reason.describe(), address
+                    );
+                    METRICS.blocked_connection(reason);
+                    continue;
+                  }
+                  let settings: Settings = settings.clone();
+                  let access: Arc<AccessControl> = Arc::clone(&access);
+                  let on_player_connection: PlayerConnectionCallback = Arc::clone(&on_player_connection);
+                  tasks.spawn(async move {
+                    if let Err(e) = handle_connection(stream, &settings, &access, &on_player_connection).await {
+                      debug!("[Java] Connection from {} closed: {}", peer, e);
+                    }
+                  });
+                }
+                Err(e) => error!("[Java] Failed to accept connection: {}", e),
+              }
+            }
+          }
+        }
+        // The listener is dropped here, releasing the port
+      })
+    );
+
+    Ok(())
+  }
+
+  /// Stops accepting connections and waits until the port is released.
+  pub async fn close(&mut self) {
+    self.closing.cancel();
+    if let Some(handle) = self.handle.take() {
+      if handle.await.is_err() {
+        error!("[Java] Sleeping server task panicked");
+      }
+      info!("[Java] Sleeping server closed");
+    }
+  }
+}
+
+fn client_timeout() -> Duration {
+  Duration::from_millis(MC_TIMEOUT as u64)
+}
+
+async fn handle_connection(
+  mut stream: TcpStream,
+  settings: &Settings,
+  access: &AccessControl,
+  on_player_connection: &PlayerConnectionCallback
+) -> io::Result<()> {
+  let mut first_byte: [u8; 1] = [0];
+  let peeked: usize = timeout(client_timeout(), stream.peek(&mut first_byte)).await.map_err(|_| timed_out())??;
+  if peeked == 1 && first_byte[0] == LEGACY_PING_PACKET_ID {
+    return handle_legacy_ping(&mut stream, settings).await;
+  }
+
+  let (id, mut data) = read_packet(&mut stream).await?;
+  if id != HANDSHAKE_PACKET_ID {
+    return Err(invalid_data("Expected a handshake packet"));
+  }
+  let handshake: Handshake = Handshake::read(&mut data).await?;
+  debug!(
+    "[Java] Handshake from {} (protocol {}, address {}:{}, next state {})",
+    stream.peer_addr()?,
+    handshake.protocol_version,
+    handshake.server_address,
+    handshake.server_port,
+    handshake.next_state
+  );
+
+  match handshake.next_state {
+    NEXT_STATE_STATUS => handle_status(&mut stream, settings, &handshake).await,
+    NEXT_STATE_LOGIN => handle_login(&mut stream, settings, access, &handshake, on_player_connection).await,
+    _ => Err(invalid_data("Unknown handshake next state")),
+  }
+}
+
+/// Answers the pre-1.7 server list ping (`0xFE`, `0xFE01` and `0xFE01FA`) with the `§1` kick format,
+/// understood by every client from 1.4 onwards.
+async fn handle_legacy_ping(stream: &mut TcpStream, settings: &Settings) -> io::Result<()> {
+  // Everything the client sends fits in the first segment, we only need it out of the socket buffer
+  let mut request: [u8; 512] = [0; 512];
+  let length: usize = timeout(client_timeout(), stream.read(&mut request)).await.map_err(|_| timed_out())??;
+  debug!("[Java] Legacy ping from {} ({} bytes)", stream.peer_addr()?, length);
+  METRICS.status_ping(Edition::Java);
+
+  let motd: String = match get_motd(settings, MotdOutputType::PlainText) {
+    Some(MotdOutput::PlainText(motd)) => motd,
+    _ => clean_tags(&settings.serverName),
+  };
+  let (version_name, _) = advertised_version(settings, None);
+  let response: String = format!(
+    "§1\0{}\0{}\0{}\0{}\0{}",
+    LEGACY_PROTOCOL_VERSION,
+    version_name,
+    motd,
+    0,
+    settings.maxPlayers
+  );
+  let response: Vec<u16> = response.encode_utf16().collect();
+
+  let mut packet: Vec<u8> = vec![LEGACY_KICK_PACKET_ID];
+  packet.extend_from_slice(&(response.len() as u16).to_be_bytes());
+  for unit in response {
+    packet.extend_from_slice(&unit.to_be_bytes());
+  }
+
+  stream.write_all(&packet).await?;
+  stream.flush().await
+}
+
+/// Version name and protocol advertised to a client, according to the `version` setting:
+/// `true` echoes the client's own protocol, a string advertises that release and anything else
+/// advertises the latest supported release.
+fn advertised_version(settings: &Settings, client_protocol: Option<i32>) -> (String, i32) {
+  let latest_protocol: i32 = get_protocol(LATEST_MINECRAFT_VERSION).unwrap_or(LEGACY_PROTOCOL_VERSION);
+
+  match (&settings.version, client_protocol) {
+    (Some(Version::Bool(true)), Some(protocol)) => {
+      let name: &str = get_version_name(protocol).unwrap_or(LATEST_MINECRAFT_VERSION);
+      (String::from(name), protocol)
+    }
+    (Some(Version::String(name)), _) => {
+      match get_protocol(name) {
+        Some(protocol) => (name.clone(), protocol),
+        None => {
+          warn!("[Java] Unknown version {} in settings, using the client's protocol", name);
+          (name.clone(), client_protocol.unwrap_or(latest_protocol))
+        }
+      }
+    }
+    _ => (String::from(LATEST_MINECRAFT_VERSION), latest_protocol),
+  }
+}
+
+fn status_response(settings: &Settings, handshake: &Handshake) -> Value {
+  let (version_name, protocol) = advertised_version(settings, Some(handshake.protocol_version));
+  let description: Value = match get_motd(settings, MotdOutputType::JSON) {
+    Some(MotdOutput::JSON(motd)) => motd,
+    _ => json!({ "text": settings.serverName }),
+  };
+
+  json!({
+    "version": {
+      "name": version_name,
+      "protocol": protocol,
+    },
+    "players": {
+      "max": settings.maxPlayers,
+      "online": 0,
+      "sample": [],
+    },
+    "description": description,
+    "favicon": get_fav_icon(settings),
+  })
+}
+
+async fn handle_status(stream: &mut TcpStream, settings: &Settings, handshake: &Handshake) -> io::Result<()> {
+  loop {
+    let (id, mut data) = read_packet(stream).await?;
+    match id {
+      STATUS_REQUEST_PACKET_ID => {
+        debug!("[Java] Status request from {}", stream.peer_addr()?);
+        METRICS.status_ping(Edition::Java);
+        let mut response: Vec<u8> = Vec::new();
+        write_string(&mut response, &status_response(settings, handshake).to_string());
+        send_packet(stream, STATUS_RESPONSE_PACKET_ID, &response).await?;
+      }
+      PING_PACKET_ID => {
+        let mut payload: [u8; 8] = [0; 8];
+        data.read_exact(&mut payload).await?;
+        send_packet(stream, PONG_PACKET_ID, &payload).await?;
+        return Ok(());
+      }
+      _ => {
+        return Err(invalid_data("Unexpected packet in status state"));
+      }
+    }
+  }
+}
+
+async fn handle_login(
+  stream: &mut TcpStream,
+  settings: &Settings,
+  access: &AccessControl,
+  handshake: &Handshake,
+  on_player_connection: &PlayerConnectionCallback
+) -> io::Result<()> {
+  let (id, mut data) = read_packet(stream).await?;
+  if id != LOGIN_START_PACKET_ID {
+    return Err(invalid_data("Expected a login start packet"));
+  }
+  let player_name: String = read_string(&mut data).await?;
+  let address: String = display_address(settings, &stream.peer_addr()?.ip());
+  log_fields!(
+    info, player = player_name.as_str(), address = address.as_str();
+    "[Java] Player {} tried to join the server with {}", player_name, describe_protocol(handshake.protocol_version)
+  );
+
+  if let Err(refusal) = access.check_player(&player_name) {
+    return match refusal {
+      PlayerRefusal::NotWhitelisted => {
+        log_fields!(
+          info, player = player_name.as_str(), address = address.as_str(), reason = BlockReason::Whitelist.describe();
+          "[Java] Player {} from {} is not whitelisted, not waking the server", player_name, address
+        );
+        METRICS.blocked_connection(BlockReason::Whitelist);
+        disconnect(stream, settings.whiteListMessage.as_deref().unwrap_or(NOT_WHITELISTED_MESSAGE)).await
+      }
+      PlayerRefusal::Banned(reason) => {
+        log_fields!(
+          info, player = player_name.as_str(), address = address.as_str(), reason = BlockReason::Banned.describe();
+          "[Java] Player {} from {} is banned, not waking the server", player_name, address
+        );
+        METRICS.blocked_connection(BlockReason::Banned);
+        disconnect(stream, &format!("You are banned from this server.\nReason: {}", reason)).await
+      }
+    };
+  }
+
+  disconnect(stream, &settings.loginMessage).await?;
+  on_player_connection(Player { name: player_name, edition: Edition::Java });
+  Ok(())
+}
+
+/// Kicks a client in the login state with `message`, which may contain formatting codes.
+async fn disconnect(stream: &mut TcpStream, message: &str) -> io::Result<()> {
+  let reason: Value = text_to_json(message);
+  let mut response: Vec<u8> = Vec::new();
+  write_string(&mut response, &reason.to_string());
+  send_packet(stream, LOGIN_DISCONNECT_PACKET_ID, &response).await
+}
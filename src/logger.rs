@@ -1,15 +1,169 @@
-use simplelog::{CombinedLogger, TermLogger, WriteLogger, LevelFilter, Level, TerminalMode, ColorChoice, ConfigBuilder, Color};
-use std::{fs::{File,metadata,rename}, path::Path};
-use chrono::{DateTime, Local, NaiveDateTime};
+use simplelog::{TermLogger, WriteLogger, LevelFilter, Level, TerminalMode, ColorChoice, ConfigBuilder, Color};
+use log::{Log, Metadata, Record, kv::{self, Key, Value as KvValue}};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::{
+  borrow::Cow,
+  cmp::Reverse,
+  fs::{self, File},
+  io::{self, Write},
+  mem,
+  net::{IpAddr, Ipv6Addr},
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex, MutexGuard, RwLock, atomic::{AtomicUsize, Ordering}},
+  thread,
+  time::{Duration, SystemTime},
+};
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat};
+use flate2::{Compression, write::GzEncoder};
+use serde_json::{Map, Value};
 
 use crate::constants::{LOG_PATH, LOG_FILE};
+use crate::settings::{IpRedaction, LogFormat, LogRotation};
+
+/// Limits of the log files, none until `logRotation` is read from the settings.
+static LOG_ROTATION: RwLock<LogRotation> = RwLock::new(LogRotation {
+  maxSizeMegabytes: None,
+  maxFiles: None,
+  maxAgeDays: None,
+});
+
+/// Archives of `latest.log`, named like the vanilla server ones.
+static ARCHIVE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}-\d+\.log\.gz$").unwrap());
+
+/// Format of the log lines, text until `logFormat` or `--log-format` says otherwise.
+static LOG_FORMAT: RwLock<LogFormat> = RwLock::new(LogFormat::Text);
+
+/// Masking applied to the addresses in every log line, `None` until `hideIpInLogs` is read from the settings.
+static IP_REDACTION: RwLock<Option<IpRedaction>> = RwLock::new(None);
+
+static IPV4_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b").unwrap());
+
+/// Anything shaped like an IPv6 address, possibly ending with an IPv4 one. Checked by parsing it afterwards.
+static IPV6_REGEX: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r"(?:[0-9A-Fa-f]{0,4}:){2,7}(?:(?:\d{1,3}\.){3}\d{1,3}|[0-9A-Fa-f]{1,4})?").unwrap()
+});
+
+/// Levels of the terminal and the log file, as `LevelFilter` values since they can change at runtime.
+static TERMINAL_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Info as usize);
+static FILE_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Debug as usize);
+
+/// Console command changing the log levels, handled by crabslumber instead of being sent to Minecraft.
+const LOG_LEVEL_COMMAND: &str = "!loglevel";
 
 pub struct LoggerSettings {
   pub level: LevelFilter,
   pub file_level: LevelFilter,
 }
 
-pub fn init_logger(settings: LoggerSettings) -> () {
+/// Where the logs go, each with a level of its own.
+#[derive(Clone, Copy, Debug)]
+pub enum LogOutput {
+  Terminal,
+  File,
+}
+
+impl LogOutput {
+  fn level(&self) -> &'static AtomicUsize {
+    match self {
+      LogOutput::Terminal => &TERMINAL_LEVEL,
+      LogOutput::File => &FILE_LEVEL,
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      LogOutput::Terminal => "terminal",
+      LogOutput::File => "file",
+    }
+  }
+}
+
+/// Masks the addresses in the messages before handing them to the terminal and file loggers, so the Minecraft
+/// output and every other line go through the same redaction. Both loggers accept everything, the levels are
+/// checked here so they can change without building them again. JSON lines are written here directly.
+struct SleepingLogger {
+  terminal: Box<TermLogger>,
+  /// Formats the text lines into `record`, which are then written to `log_file` in one go.
+  file: Box<WriteLogger<RecordBuffer>>,
+  record: RecordBuffer,
+  log_file: Mutex<RotatingFile>,
+}
+
+impl SleepingLogger {
+  fn dispatch(&self, record: &Record) {
+    let to_terminal: bool = record.level() <= get_log_level(LogOutput::Terminal);
+    let to_file: bool = record.level() <= get_log_level(LogOutput::File);
+    let format: LogFormat = *LOG_FORMAT.read().unwrap();
+    match format {
+      LogFormat::Text => {
+        if to_terminal {
+          self.terminal.log(record);
+        }
+        if to_file {
+          // Held while formatting so the buffer only ever holds this record
+          let mut log_file: MutexGuard<RotatingFile> = self.log_file.lock().unwrap();
+          self.file.log(record);
+          let line: Vec<u8> = mem::take(&mut *self.record.0.lock().unwrap());
+          let _ = log_file.write_record(&line);
+        }
+      }
+      LogFormat::Json => {
+        let line: String = format!("{}\n", json_line(record));
+        if to_terminal {
+          let _ = io::stdout().lock().write_all(line.as_bytes());
+        }
+        if to_file {
+          let _ = self.log_file.lock().unwrap().write_record(line.as_bytes());
+        }
+      }
+    }
+  }
+}
+
+impl Log for SleepingLogger {
+  fn enabled(&self, metadata: &Metadata) -> bool {
+    metadata.level() <= get_log_level(LogOutput::Terminal).max(get_log_level(LogOutput::File))
+  }
+
+  fn log(&self, record: &Record) {
+    if !self.enabled(record.metadata()) {
+      return;
+    }
+    let redaction: Option<IpRedaction> = *IP_REDACTION.read().unwrap();
+    let redaction: IpRedaction = match redaction {
+      Some(redaction) => redaction,
+      None => {
+        return self.dispatch(record);
+      }
+    };
+
+    let message: String = record.args().to_string();
+    match redact_addresses(&message, redaction) {
+      Cow::Borrowed(_) => self.dispatch(record),
+      Cow::Owned(redacted) => {
+        self.dispatch(
+          &Record::builder()
+            .args(format_args!("{}", redacted))
+            .metadata(record.metadata().clone())
+            .module_path(record.module_path())
+            .file(record.file())
+            .line(record.line())
+            .key_values(record.key_values())
+            .build()
+        )
+      }
+    }
+  }
+
+  fn flush(&self) {
+    self.terminal.flush();
+    let _ = self.log_file.lock().unwrap().file.flush();
+    let _ = io::stdout().flush();
+  }
+}
+
+pub fn init_logger(settings: LoggerSettings) {
   let config = ConfigBuilder::new()
     .set_level_color(Level::Trace, Some(Color::Magenta))
     .set_level_color(Level::Debug, Some(Color::Cyan))
@@ -19,19 +173,588 @@ pub fn init_logger(settings: LoggerSettings) -> () {
     .build();
 
   if !Path::new(&LOG_PATH).exists() {
-    std::fs::create_dir(&LOG_PATH).unwrap();
+    std::fs::create_dir(LOG_PATH).unwrap();
+  }
+
+  let terminal: Box<TermLogger> = TermLogger::new(LevelFilter::Trace, config.clone(), TerminalMode::Mixed, ColorChoice::Auto);
+  let record: RecordBuffer = RecordBuffer(Arc::new(Mutex::new(Vec::new())));
+  let file: Box<WriteLogger<RecordBuffer>> = WriteLogger::new(LevelFilter::Trace, config, record.clone());
+  let log_file: Mutex<RotatingFile> = Mutex::new(RotatingFile::open(Path::new(LOG_PATH)).unwrap());
+  set_log_level(LogOutput::Terminal, settings.level);
+  set_log_level(LogOutput::File, settings.file_level);
+  log::set_boxed_logger(Box::new(SleepingLogger { terminal, file, record, log_file })).unwrap();
+}
+
+/// Logs like the `simplelog` macros, with fields the JSON format writes next to the message, as in
+/// `log_fields!(info, player = name, address = address; "[Java] Player {} ...", name)`. The text format only shows
+/// the message. The fields are the key-values of `log`, which are unstable, so they are only used through here.
+macro_rules! log_fields {
+  ($level:ident, $($key:ident = $value:expr),+; $($message:tt)+) => {
+    log::$level!($($key = $value),+; $($message)+)
+  };
+}
+pub(crate) use log_fields;
+
+/// Switches the terminal and the log file to `format`.
+pub fn set_log_format(format: LogFormat) {
+  *LOG_FORMAT.write().unwrap() = format;
+}
+
+pub fn get_log_level(output: LogOutput) -> LevelFilter {
+  let level: usize = output.level().load(Ordering::Relaxed);
+  LevelFilter::iter().nth(level).unwrap_or(LevelFilter::Off)
+}
+
+/// Changes the level of `output` right away, without restarting.
+pub fn set_log_level(output: LogOutput, level: LevelFilter) {
+  output.level().store(level as usize, Ordering::Relaxed);
+  log::set_max_level(get_log_level(LogOutput::Terminal).max(get_log_level(LogOutput::File)));
+}
+
+/// Switches `output` to the next more verbose level, going back to `OFF` after `TRACE`. Returns the new level.
+pub fn cycle_log_level(output: LogOutput) -> LevelFilter {
+  let level: LevelFilter = LevelFilter::iter()
+    .nth(get_log_level(output) as usize + 1)
+    .unwrap_or(LevelFilter::Off);
+  set_log_level(output, level);
+  level
+}
+
+/// Runs `!loglevel` or `!loglevel terminal|file|all level` typed in a console, returns what to answer. `None` if `command`
+/// is something else, to be sent to Minecraft.
+pub fn log_level_command(command: &str) -> Option<String> {
+  let mut words = command.split_whitespace();
+  if words.next() != Some(LOG_LEVEL_COMMAND) {
+    return None;
   }
 
-  let latest_log_path: String = format!("{}/{}", &LOG_PATH, &LOG_FILE);
-  if Path::new(&latest_log_path).exists() {
-    let metadata = metadata(&latest_log_path).unwrap();
-    let created: DateTime<Local> = metadata.created().unwrap().into();
-    let datetime: NaiveDateTime = created.naive_local();
-    rename(&latest_log_path, format!("{}/{}.log", &LOG_PATH, datetime.format("%Y-%m-%d-%H-%M-%S").to_string())).unwrap();
+  let outputs: Vec<LogOutput> = match words.next().map(|word| word.to_lowercase()).as_deref() {
+    None => {
+      return Some(format!(
+        "Log levels: terminal {}, file {}",
+        get_log_level(LogOutput::Terminal),
+        get_log_level(LogOutput::File)
+      ));
+    }
+    Some("terminal") => vec![LogOutput::Terminal],
+    Some("file") => vec![LogOutput::File],
+    Some("all") => vec![LogOutput::Terminal, LogOutput::File],
+    Some(_) => {
+      return Some(log_level_usage());
+    }
+  };
+  let level: LevelFilter = match (words.next().map(|word| word.parse::<LevelFilter>()), words.next()) {
+    (Some(Ok(level)), None) => level,
+    _ => {
+      return Some(log_level_usage());
+    }
+  };
+
+  let changed: Vec<String> = outputs
+    .into_iter()
+    .map(|output| {
+      set_log_level(output, level);
+      format!("Log level of the {} set to {}", output.name(), level)
+    })
+    .collect();
+  Some(changed.join(", "))
+}
+
+fn log_level_usage() -> String {
+  format!(
+    "Usage: {} to show the log levels, {} terminal|file|all off|error|warn|info|debug|trace to change them",
+    LOG_LEVEL_COMMAND,
+    LOG_LEVEL_COMMAND
+  )
+}
+
+/// Applies `logRotation`, deleting the archives it doesn't keep.
+pub fn set_log_rotation(rotation: LogRotation) {
+  *LOG_ROTATION.write().unwrap() = rotation.clone();
+  thread::spawn(move || prune_archives(Path::new(LOG_PATH), &rotation));
+}
+
+/// Starts masking the addresses in the logs, or stops with `None`.
+pub fn set_ip_redaction(redaction: Option<IpRedaction>) {
+  *IP_REDACTION.write().unwrap() = redaction;
+}
+
+/// How an address appears in the logs with `redaction`.
+pub fn redact_address(address: &IpAddr, redaction: IpRedaction) -> String {
+  let address: IpAddr = match address {
+    IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(*address),
+    IpAddr::V4(_) => *address,
+  };
+
+  match (redaction, address) {
+    (IpRedaction::Full, _) => String::from("[hidden]"),
+    (IpRedaction::Partial, IpAddr::V4(v4)) => {
+      let octets: [u8; 4] = v4.octets();
+      format!("{}.{}.x.x", octets[0], octets[1])
+    }
+    (IpRedaction::Partial, IpAddr::V6(v6)) => {
+      let segments: [u16; 8] = v6.segments();
+      format!("{:x}:{:x}:{:x}:x:x:x:x:x", segments[0], segments[1], segments[2])
+    }
   }
+}
+
+/// The record as a JSON object, its key-values next to the timestamp, level, module and message.
+fn json_line(record: &Record) -> String {
+  let mut fields: Map<String, Value> = Map::new();
+  fields.insert(String::from("timestamp"), Value::from(Local::now().to_rfc3339_opts(SecondsFormat::Millis, false)));
+  fields.insert(String::from("level"), Value::from(record.level().to_string()));
+  fields.insert(String::from("module"), Value::from(record.module_path().unwrap_or(record.target())));
+  fields.insert(String::from("message"), Value::from(record.args().to_string()));
+
+  let redaction: Option<IpRedaction> = *IP_REDACTION.read().unwrap();
+  let _ = record.key_values().visit(&mut JsonFields { fields: &mut fields, redaction });
+  Value::Object(fields).to_string()
+}
 
-  CombinedLogger::init(vec![
-    TermLogger::new(settings.level, config.clone(), TerminalMode::Mixed, ColorChoice::Auto),
-    WriteLogger::new(settings.file_level, config, File::create(&latest_log_path).unwrap()),
-  ]).unwrap();
-}
\ No newline at end of file
+/// Adds the key-values of a record to its JSON object, without replacing the fields every line has.
+struct JsonFields<'a> {
+  fields: &'a mut Map<String, Value>,
+  redaction: Option<IpRedaction>,
+}
+
+impl<'kvs> kv::Visitor<'kvs> for JsonFields<'_> {
+  fn visit_pair(&mut self, key: Key<'kvs>, value: KvValue<'kvs>) -> Result<(), kv::Error> {
+    if self.fields.contains_key(key.as_str()) {
+      return Ok(());
+    }
+    let value: Value = if let Some(number) = value.to_i64() {
+      Value::from(number)
+    } else if let Some(boolean) = value.to_bool() {
+      Value::from(boolean)
+    } else {
+      let text: String = value.to_string();
+      match self.redaction {
+        Some(redaction) => Value::from(redact_addresses(&text, redaction).into_owned()),
+        None => Value::from(text),
+      }
+    };
+    self.fields.insert(String::from(key.as_str()), value);
+    Ok(())
+  }
+}
+
+/// Masks the addresses of a line shown outside of the logs, as `hideIpInLogs` asks. Borrowed back if there is
+/// nothing to mask.
+pub fn redact_line(line: &str) -> Cow<'_, str> {
+  let redaction: Option<IpRedaction> = *IP_REDACTION.read().unwrap();
+  match redaction {
+    Some(redaction) => redact_addresses(line, redaction),
+    None => Cow::Borrowed(line),
+  }
+}
+
+/// Replaces every IPv4 and IPv6 address of a log line, borrowed back if there was none.
+fn redact_addresses(message: &str, redaction: IpRedaction) -> Cow<'_, str> {
+  // IPv6 first, mapped addresses like ::ffff:1.2.3.4 would be cut in two otherwise
+  let without_ipv6: Cow<str> = IPV6_REGEX.replace_all(message, |captures: &Captures| {
+    let candidate: regex::Match = captures.get(0).unwrap();
+    // Rust paths (std::io), timestamps (12:00:00) and the like aren't addresses
+    let is_word_part: bool = message[..candidate.start()].ends_with(|c: char| c.is_alphanumeric())
+      || message[candidate.end()..].starts_with(|c: char| c.is_alphanumeric());
+    let has_hex_digit: bool = candidate.as_str().contains(|c: char| c.is_ascii_hexdigit());
+    match candidate.as_str().parse::<Ipv6Addr>() {
+      Ok(address) if !is_word_part && has_hex_digit => redact_address(&IpAddr::V6(address), redaction),
+      _ => String::from(candidate.as_str()),
+    }
+  });
+
+  let redacted: Cow<str> = IPV4_REGEX.replace_all(&without_ipv6, |captures: &Captures| {
+    let candidate: regex::Match = captures.get(0).unwrap();
+    if is_version(&without_ipv6[..candidate.start()], &without_ipv6[candidate.end()..]) {
+      return String::from(candidate.as_str());
+    }
+    match candidate.as_str().parse::<IpAddr>() {
+      Ok(address) => redact_address(&address, redaction),
+      Err(_) => String::from(candidate.as_str()),
+    }
+  });
+  if let Cow::Owned(redacted) = redacted {
+    return Cow::Owned(redacted);
+  }
+  without_ipv6
+}
+
+/// Whether four dotted numbers between `before` and `after` are a version like Bedrock ones rather than an IPv4
+/// address: part of a longer dotted number, or right after the word "version".
+fn is_version(before: &str, after: &str) -> bool {
+  let is_dotted_part: bool = before.ends_with('.')
+    || after.strip_prefix('.').is_some_and(|after| after.starts_with(|c: char| c.is_ascii_digit()));
+  let previous_word: &str = before
+    .trim_end_matches(|c: char| c.is_whitespace() || c == ':' || c == '=')
+    .rsplit(|c: char| !c.is_alphanumeric())
+    .next()
+    .unwrap_or("");
+  is_dotted_part || previous_word.eq_ignore_ascii_case("version") || previous_word.eq_ignore_ascii_case("v")
+}
+
+/// `latest.log`, archived to `YYYY-MM-DD-N.log.gz` when the day changes or it grows past `maxSizeMegabytes`.
+struct RotatingFile {
+  /// Folder of `latest.log` and its archives.
+  directory: PathBuf,
+  file: File,
+  size: u64,
+  /// Day of the lines in the file.
+  day: NaiveDate,
+}
+
+impl RotatingFile {
+  /// Archives the `latest.log` of the last run, then starts a new one.
+  fn open(directory: &Path) -> io::Result<Self> {
+    let path: PathBuf = directory.join(LOG_FILE);
+    if let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+      let modified: DateTime<Local> = modified.into();
+      let archived: PathBuf = archive(directory, modified.date_naive())?;
+      finish_archive(directory, archived);
+    }
+
+    Ok(Self {
+      directory: directory.to_path_buf(),
+      file: File::create(&path)?,
+      size: 0,
+      day: Local::now().date_naive(),
+    })
+  }
+
+  /// Whether `length` more bytes written on `today` have to go to a new file.
+  fn needs_rotation(&self, length: usize, today: NaiveDate, max_size: Option<u64>) -> bool {
+    if today != self.day {
+      return true;
+    }
+    max_size.is_some_and(|max_size| self.size > 0 && self.size + (length as u64) > max_size)
+  }
+
+  /// Archives the file and starts a new one for `today`, returns the archive before its compression.
+  fn rotate(&mut self, today: NaiveDate) -> io::Result<PathBuf> {
+    self.file.flush()?;
+    let archived: PathBuf = archive(&self.directory, self.day)?;
+    self.file = File::create(self.directory.join(LOG_FILE))?;
+    self.size = 0;
+    self.day = today;
+    Ok(archived)
+  }
+
+  /// Writes a whole record, rotating before it if needed so a line never ends up split between two files.
+  fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+    let today: NaiveDate = Local::now().date_naive();
+    let max_size: Option<u64> = max_size(&LOG_ROTATION.read().unwrap());
+    if self.needs_rotation(record.len(), today, max_size) {
+      match self.rotate(today) {
+        Ok(archived) => finish_archive(&self.directory, archived),
+        // The logger is busy writing this very line, nothing else to report it to
+        Err(e) => eprintln!("Failed to rotate {}: {}", LOG_FILE, e),
+      }
+    }
+    self.append(record)
+  }
+
+  fn append(&mut self, record: &[u8]) -> io::Result<()> {
+    self.file.write_all(record)?;
+    self.size += record.len() as u64;
+    Ok(())
+  }
+}
+
+/// `maxSizeMegabytes` in bytes, `None` when there is no limit.
+fn max_size(rotation: &LogRotation) -> Option<u64> {
+  rotation.maxSizeMegabytes.filter(|megabytes| *megabytes > 0).map(|megabytes| megabytes << 20)
+}
+
+/// Receives a text record from `WriteLogger`, which writes it in several pieces.
+#[derive(Clone)]
+struct RecordBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for RecordBuffer {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.0.lock().unwrap().extend_from_slice(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+/// Moves `latest.log` aside as the next archive of `day`, returns where it went.
+fn archive(directory: &Path, day: NaiveDate) -> io::Result<PathBuf> {
+  let prefix: String = day.format("%Y-%m-%d").to_string();
+  let archived: PathBuf = (1..)
+    .map(|index| directory.join(format!("{}-{}.log", prefix, index)))
+    .find(|path| !path.exists() && !path.with_extension("log.gz").exists())
+    .unwrap();
+  fs::rename(directory.join(LOG_FILE), &archived)?;
+  Ok(archived)
+}
+
+/// Compresses an archive and prunes the old ones from a thread of its own.
+fn finish_archive(directory: &Path, archived: PathBuf) {
+  let directory: PathBuf = directory.to_path_buf();
+  thread::spawn(move || {
+    if let Err(e) = compress(&archived) {
+      eprintln!("Failed to compress {}: {}", archived.display(), e);
+    }
+    let rotation: LogRotation = LOG_ROTATION.read().unwrap().clone();
+    prune_archives(&directory, &rotation);
+  });
+}
+
+/// Replaces `file.log` with `file.log.gz`.
+fn compress(path: &Path) -> io::Result<()> {
+  let mut input: File = File::open(path)?;
+  let output: File = File::create(path.with_extension("log.gz"))?;
+  let mut encoder: GzEncoder<File> = GzEncoder::new(output, Compression::default());
+  io::copy(&mut input, &mut encoder)?;
+  encoder.finish()?;
+  fs::remove_file(path)
+}
+
+/// Deletes the archives beyond `maxFiles` or older than `maxAgeDays`.
+fn prune_archives(directory: &Path, rotation: &LogRotation) {
+  if rotation.maxFiles.is_none() && rotation.maxAgeDays.is_none() {
+    return;
+  }
+  let entries: fs::ReadDir = match fs::read_dir(directory) {
+    Ok(entries) => entries,
+    Err(_) => {
+      return;
+    }
+  };
+
+  let mut archives: Vec<(SystemTime, PathBuf)> = entries
+    .filter_map(|entry| entry.ok())
+    .filter(|entry| ARCHIVE_REGEX.is_match(&entry.file_name().to_string_lossy()))
+    .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+    .collect();
+  // Newest first
+  archives.sort_by_key(|(modified, _)| Reverse(*modified));
+
+  let max_age: Option<Duration> = rotation.maxAgeDays.map(|days| Duration::from_secs(days * 24 * 60 * 60));
+  for (index, (modified, path)) in archives.iter().enumerate() {
+    let is_too_many: bool = rotation.maxFiles.is_some_and(|max_files| index >= max_files);
+    let is_too_old: bool = max_age.is_some_and(|max_age| modified.elapsed().is_ok_and(|age| age > max_age));
+    if is_too_many || is_too_old {
+      if let Err(e) = fs::remove_file(path) {
+        eprintln!("Failed to delete {}: {}", path.display(), e);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{ fs::{ self, File }, io::Read, path::{ Path, PathBuf }, thread, time::{ Duration, SystemTime } };
+  use chrono::NaiveDate;
+  use flate2::read::GzDecoder;
+  use tempfile::TempDir;
+  use crate::constants::LOG_FILE;
+  use crate::settings::{ IpRedaction, LogRotation };
+  use super::{ compress, max_size, prune_archives, redact_addresses, RotatingFile };
+
+  const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+  fn file_names(directory: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(directory)
+      .unwrap()
+      .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+      .collect();
+    names.sort();
+    names
+  }
+
+  fn read_archive(path: &Path) -> String {
+    let mut content: String = String::new();
+    GzDecoder::new(File::open(path).unwrap()).read_to_string(&mut content).unwrap();
+    content
+  }
+
+  /// An empty file in `directory`, last modified `age` ago.
+  fn touch(directory: &Path, name: &str, age: Duration) {
+    let file: File = File::create(directory.join(name)).unwrap();
+    file.set_modified(SystemTime::now() - age).unwrap();
+  }
+
+  fn hidden(line: &str) -> String {
+    redact_addresses(line, IpRedaction::Full).into_owned()
+  }
+
+  #[test]
+  fn masks_ipv4_addresses() {
+    assert_eq!(hidden("[Java] Refused connection from 1.2.3.4"), "[Java] Refused connection from [hidden]");
+    assert_eq!(hidden("1.2.3.4:25565 and 10.0.0.1:19132"), "[hidden]:25565 and [hidden]:19132");
+    assert_eq!(hidden("/192.168.1.20:54321 logged in"), "/[hidden]:54321 logged in");
+    assert_eq!(redact_addresses("from 192.168.1.20:54321", IpRedaction::Partial), "from 192.168.x.x:54321");
+  }
+
+  #[test]
+  fn masks_ipv6_addresses() {
+    assert_eq!(hidden("from 2001:db8::1 joined"), "from [hidden] joined");
+    assert_eq!(hidden("from [2001:db8::1]:25565"), "from [[hidden]]:25565");
+    assert_eq!(hidden("from fe80::1%eth0"), "from [hidden]%eth0");
+    assert_eq!(hidden("from ::1"), "from [hidden]");
+    assert_eq!(
+      redact_addresses("from 2001:db8:1234:5678::1", IpRedaction::Partial),
+      "from 2001:db8:1234:x:x:x:x:x"
+    );
+  }
+
+  #[test]
+  fn masks_ipv4_mapped_addresses_whole() {
+    assert_eq!(hidden("from ::ffff:1.2.3.4"), "from [hidden]");
+    assert_eq!(hidden("from [::ffff:1.2.3.4]:25565"), "from [[hidden]]:25565");
+    assert_eq!(hidden("from ::FFFF:1.2.3.4:25565"), "from [hidden]:25565");
+    assert!(!redact_addresses("from ::ffff:1.2.3.4", IpRedaction::Partial).contains("3.4"));
+  }
+
+  #[test]
+  fn keeps_versions() {
+    for line in [
+      "Starting minecraft server version 1.20.1",
+      "Bedrock version 1.20.1.0",
+      "Version: 1.20.15.01",
+      "Running v 1.19.4.2",
+      "Build 1.20.1.0.5 is out",
+      "Player Steve tried to join the server with 1.20.1 (protocol 763)",
+    ] {
+      assert_eq!(hidden(line), line);
+    }
+  }
+
+  #[test]
+  fn keeps_timestamps_and_double_colons() {
+    for line in [
+      "[12:34:56] [Server thread/INFO]: Done (1.0s)!",
+      "2024-01-02T12:34:56.789+01:00 started",
+      "panicked at std::io::Error",
+      "failed in crabslumber::container::SleepingContainer",
+      "a :: b",
+      "ratio 16:9 or 4:3:2",
+      "deadbeef:cafe is not an address",
+    ] {
+      assert_eq!(hidden(line), line);
+    }
+  }
+
+  #[test]
+  fn borrows_lines_without_addresses() {
+    assert!(matches!(redact_addresses("nothing here", IpRedaction::Full), std::borrow::Cow::Borrowed(_)));
+  }
+
+  #[test]
+  fn rotates_when_the_day_changes() {
+    let directory: TempDir = tempfile::tempdir().unwrap();
+    let mut file: RotatingFile = RotatingFile::open(directory.path()).unwrap();
+    let today: NaiveDate = file.day;
+    let tomorrow: NaiveDate = today.succ_opt().unwrap();
+    assert!(!file.needs_rotation(10, today, None));
+    assert!(file.needs_rotation(10, tomorrow, None));
+
+    file.append(b"first day\n").unwrap();
+    let archived: PathBuf = file.rotate(tomorrow).unwrap();
+    assert_eq!(archived, directory.path().join(format!("{}-1.log", today.format("%Y-%m-%d"))));
+    assert_eq!(fs::read_to_string(&archived).unwrap(), "first day\n");
+    assert_eq!(fs::read_to_string(directory.path().join(LOG_FILE)).unwrap(), "");
+    assert_eq!((file.day, file.size), (tomorrow, 0));
+    assert!(!file.needs_rotation(10, tomorrow, None));
+  }
+
+  #[test]
+  fn rotates_before_a_record_would_pass_the_size_limit() {
+    let directory: TempDir = tempfile::tempdir().unwrap();
+    let mut file: RotatingFile = RotatingFile::open(directory.path()).unwrap();
+    let today: NaiveDate = file.day;
+    // An empty file takes any record, it would never fit anywhere otherwise
+    assert!(!file.needs_rotation(1000, today, Some(100)));
+
+    file.append(&[b'a'; 60]).unwrap();
+    assert!(!file.needs_rotation(40, today, Some(100)));
+    assert!(file.needs_rotation(41, today, Some(100)));
+    assert!(!file.needs_rotation(1000, today, None));
+
+    assert_eq!(max_size(&LogRotation { maxSizeMegabytes: Some(2), ..LogRotation::default() }), Some(2 << 20));
+    assert_eq!(max_size(&LogRotation { maxSizeMegabytes: Some(0), ..LogRotation::default() }), None);
+    assert_eq!(max_size(&LogRotation::default()), None);
+  }
+
+  #[test]
+  fn numbers_the_archives_of_a_day() {
+    let directory: TempDir = tempfile::tempdir().unwrap();
+    let mut file: RotatingFile = RotatingFile::open(directory.path()).unwrap();
+    let today: NaiveDate = file.day;
+    let prefix: String = today.format("%Y-%m-%d").to_string();
+
+    for index in 1..=3 {
+      file.append(format!("part {}\n", index).as_bytes()).unwrap();
+      file.rotate(today).unwrap();
+    }
+    assert_eq!(
+      file_names(directory.path()),
+      [format!("{}-1.log", prefix), format!("{}-2.log", prefix), format!("{}-3.log", prefix), String::from(LOG_FILE)]
+    );
+
+    compress(&directory.path().join(format!("{}-1.log", prefix))).unwrap();
+    assert_eq!(read_archive(&directory.path().join(format!("{}-1.log.gz", prefix))), "part 1\n");
+    assert!(!directory.path().join(format!("{}-1.log", prefix)).exists());
+
+    // A compressed archive keeps its number taken
+    fs::remove_file(directory.path().join(format!("{}-2.log", prefix))).unwrap();
+    fs::remove_file(directory.path().join(format!("{}-3.log", prefix))).unwrap();
+    assert_eq!(file.rotate(today).unwrap(), directory.path().join(format!("{}-2.log", prefix)));
+  }
+
+  #[test]
+  fn archives_the_last_run_when_opening() {
+    let directory: TempDir = tempfile::tempdir().unwrap();
+    fs::write(directory.path().join(LOG_FILE), "last run\n").unwrap();
+    let day: NaiveDate = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+    File::options()
+      .write(true)
+      .open(directory.path().join(LOG_FILE))
+      .unwrap()
+      .set_modified(day.and_hms_opt(12, 0, 0).unwrap().and_local_timezone(chrono::Local).unwrap().into())
+      .unwrap();
+
+    let _file: RotatingFile = RotatingFile::open(directory.path()).unwrap();
+    assert_eq!(fs::read_to_string(directory.path().join(LOG_FILE)).unwrap(), "");
+
+    // Compressed from a thread of its own
+    let archive: PathBuf = directory.path().join("2024-01-02-1.log.gz");
+    for _ in 0..50 {
+      if archive.exists() && !directory.path().join("2024-01-02-1.log").exists() {
+        break;
+      }
+      thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(file_names(directory.path()), ["2024-01-02-1.log.gz", LOG_FILE]);
+    assert_eq!(read_archive(&archive), "last run\n");
+  }
+
+  #[test]
+  fn prunes_archives_by_count_and_age() {
+    let directory: TempDir = tempfile::tempdir().unwrap();
+    for (index, name) in ["2024-01-05-1.log.gz", "2024-01-04-2.log.gz", "2024-01-04-1.log.gz", "2024-01-03-1.log.gz"]
+      .into_iter()
+      .enumerate()
+    {
+      touch(directory.path(), name, DAY * (index as u32) + Duration::from_secs(60 * 60));
+    }
+    touch(directory.path(), "notes.txt", DAY * 30);
+    touch(directory.path(), LOG_FILE, DAY * 30);
+
+    prune_archives(directory.path(), &LogRotation::default());
+    assert_eq!(file_names(directory.path()).len(), 6);
+
+    prune_archives(directory.path(), &LogRotation { maxFiles: Some(3), ..LogRotation::default() });
+    assert_eq!(
+      file_names(directory.path()),
+      ["2024-01-04-1.log.gz", "2024-01-04-2.log.gz", "2024-01-05-1.log.gz", LOG_FILE, "notes.txt"]
+    );
+
+    prune_archives(directory.path(), &LogRotation { maxAgeDays: Some(1), ..LogRotation::default() });
+    assert_eq!(file_names(directory.path()), ["2024-01-05-1.log.gz", LOG_FILE, "notes.txt"]);
+  }
+}
@@ -1,23 +1,34 @@
 pub mod motd_parser;
+pub mod access;
+pub mod auth;
 pub mod constants;
 pub mod container;
 pub mod settings;
 pub mod bedrock;
 pub mod discord;
+pub mod events;
 pub mod helper;
 pub mod logger;
 pub mod java;
+pub mod metrics;
+pub mod versions;
 pub mod web;
 
-use signal_hook::{ iterator::Signals, consts::{ SIGINT, SIGTERM } };
-use simplelog::{ error, info, debug, warn, trace };
-use std::{ thread, process::exit };
-use logger::init_logger;
+use signal_hook::{ iterator::Signals, consts::{ SIGINT, SIGTERM, SIGUSR1, SIGUSR2 } };
+use simplelog::{ error, info, warn };
+use std::{ io, process::exit, sync::Arc, thread };
+use tokio::runtime::Handle;
+use logger::{ init_logger, cycle_log_level, log_level_command, set_ip_redaction, set_log_format, set_log_rotation, LogOutput };
 use clap::{ Arg, Command, ArgAction, value_parser };
 
+use crate::auth::hash_password;
 use crate::logger::LoggerSettings;
+use crate::settings::{ get_settings, LogFormat, Settings };
+use crate::container::SleepingContainer;
+use crate::helper::get_ip_redaction;
 
-fn main() {
+#[tokio::main]
+async fn main() {
   let mut logger_settings = LoggerSettings {
     level: simplelog::LevelFilter::Info,
     file_level: simplelog::LevelFilter::Debug,
@@ -48,7 +59,7 @@ fn main() {
         .default_value("false")
         .value_parser(value_parser!(bool))
         .action(ArgAction::SetTrue)
-        .conflicts_with_all(&["debug", "error", "warn", "info", "off"])
+        .conflicts_with_all(["debug", "error", "warn", "info", "off"])
         .help("Sets the logging level to trace")
     )
     .arg(
@@ -56,7 +67,7 @@ fn main() {
         .long("debug")
         .num_args(0)
         .action(ArgAction::SetTrue)
-        .conflicts_with_all(&["trace", "error", "warn", "info", "off"])
+        .conflicts_with_all(["trace", "error", "warn", "info", "off"])
         .help("Sets the logging level to debug")
     )
     .arg(
@@ -64,7 +75,7 @@ fn main() {
         .long("error")
         .num_args(0)
         .action(ArgAction::SetTrue)
-        .conflicts_with_all(&["trace", "debug", "warn", "info", "off"])
+        .conflicts_with_all(["trace", "debug", "warn", "info", "off"])
         .help("Sets the logging level to error")
     )
     .arg(
@@ -72,7 +83,7 @@ fn main() {
         .long("warn")
         .num_args(0)
         .action(ArgAction::SetTrue)
-        .conflicts_with_all(&["trace", "debug", "error", "info", "off"])
+        .conflicts_with_all(["trace", "debug", "error", "info", "off"])
         .help("Sets the logging level to warn")
     )
     .arg(
@@ -80,7 +91,7 @@ fn main() {
         .long("info")
         .num_args(0)
         .action(ArgAction::SetTrue)
-        .conflicts_with_all(&["trace", "debug", "error", "warn", "off"])
+        .conflicts_with_all(["trace", "debug", "error", "warn", "off"])
         .help("Sets the logging level to info (default)")
     )
     .arg(
@@ -88,7 +99,7 @@ fn main() {
         .long("off")
         .num_args(0)
         .action(ArgAction::SetTrue)
-        .conflicts_with_all(&["trace", "debug", "error", "warn", "info"])
+        .conflicts_with_all(["trace", "debug", "error", "warn", "info"])
         .help("Sets the logging level to off")
     )
     .arg(
@@ -107,8 +118,38 @@ fn main() {
         .conflicts_with("trace-file")
         .help("Disables the log file")
     )
+    .arg(
+      Arg::new("log-format")
+        .long("log-format")
+        .num_args(1)
+        .value_parser(["text", "json"])
+        .help("Writes the logs as text or as JSON lines, overrides logFormat")
+    )
+    .arg(
+      Arg::new("hash-password")
+        .long("hash-password")
+        .num_args(0)
+        .action(ArgAction::SetTrue)
+        .help("Reads a password from stdin, prints its hash for the passwordHash of a webAuth user and exits")
+    )
     .get_matches();
 
+  if matches.get_flag("hash-password") {
+    let mut password: String = String::new();
+    if let Err(e) = io::stdin().read_line(&mut password) {
+      eprintln!("Failed to read the password: {}", e);
+      exit(1);
+    }
+    match hash_password(password.trim_end_matches(['\r', '\n'])) {
+      Ok(hash) => println!("{}", hash),
+      Err(e) => {
+        eprintln!("Failed to hash the password: {}", e);
+        exit(1);
+      }
+    }
+    exit(0);
+  }
+
   if matches.get_flag("trace") {
     logger_settings.level = simplelog::LevelFilter::Trace;
     level = simplelog::LevelFilter::Trace;
@@ -138,6 +179,15 @@ fn main() {
     file_level = simplelog::LevelFilter::Debug;
   }
 
+  // Applied before the logger starts so every line has the same format
+  let log_format: Option<LogFormat> = matches.get_one::<String>("log-format").map(|format| match format.as_str() {
+    "json" => LogFormat::Json,
+    _ => LogFormat::Text,
+  });
+  if let Some(format) = log_format {
+    set_log_format(format);
+  }
+
   init_logger(logger_settings);
 
   match level {
@@ -156,22 +206,73 @@ fn main() {
     _ => (),
   }
 
-  trace!("Hello, world!");
-  debug!("Hello, world!");
-  info!("Hello, world!");
-  warn!("Hello, world!");
-  error!("Hello, world!");
+  let settings: Settings = get_settings();
+  set_ip_redaction(get_ip_redaction(&settings));
+  set_log_rotation(settings.logRotation.clone().unwrap_or_default());
+  set_log_format(log_format.or(settings.logFormat).unwrap_or_default());
 
-  let mut signals: signal_hook::iterator::SignalsInfo = Signals::new(&[SIGINT, SIGTERM]).unwrap();
+  let container: Arc<SleepingContainer> = SleepingContainer::new(settings);
+  if let Err(e) = container.init().await {
+    error!("[Container] Failed to start the sleeping servers: {}", e);
+    exit(1);
+  }
 
+  // Console commands typed in our terminal go to the Minecraft server, except `!loglevel`. stdin is read from a
+  // thread of its own since reading it blocks
+  let console_container: Arc<SleepingContainer> = Arc::clone(&container);
+  let runtime: Handle = Handle::current();
   thread::spawn(move || {
+    for line in io::stdin().lines() {
+      match line {
+        Ok(line) if line.trim().is_empty() => {}
+        Ok(line) => {
+          if let Some(answer) = log_level_command(&line) {
+            info!("[Console] {}", answer);
+            continue;
+          }
+          if let Err(e) = runtime.block_on(console_container.send_command(&line)) {
+            warn!("[Console] Could not send command: {}", e);
+          }
+        }
+        Err(e) => {
+          error!("[Console] Failed to read from stdin: {}", e);
+          break;
+        }
+      }
+    }
+  });
+
+  // The first SIGINT or SIGTERM shuts down gracefully, a second one exits right away. SIGUSR1 and SIGUSR2 cycle
+  // the terminal and file log levels.
+  let signal_container: Arc<SleepingContainer> = Arc::clone(&container);
+  let mut signals: signal_hook::iterator::SignalsInfo = Signals::new([SIGINT, SIGTERM, SIGUSR1, SIGUSR2]).unwrap();
+  thread::spawn(move || {
+    let mut is_shutting_down: bool = false;
     for sig in signals.forever() {
       match sig {
         SIGINT => info!("Received SIGINT"),
         SIGTERM => info!("Received SIGTERM"),
+        SIGUSR1 => {
+          let level: simplelog::LevelFilter = cycle_log_level(LogOutput::Terminal);
+          info!("Received SIGUSR1, terminal log level set to {}", level);
+          continue;
+        }
+        SIGUSR2 => {
+          let level: simplelog::LevelFilter = cycle_log_level(LogOutput::File);
+          info!("Received SIGUSR2, file log level set to {}", level);
+          continue;
+        }
         _ => unreachable!(),
       }
-      exit(0);
+      if is_shutting_down {
+        warn!("Received a second signal, exiting without waiting");
+        exit(1);
+      }
+      is_shutting_down = true;
+      signal_container.request_shutdown();
     }
   });
+
+  container.wait_for_shutdown().await;
+  exit(0);
 }
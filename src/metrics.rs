@@ -0,0 +1,234 @@
+use std::{
+  fmt::Write,
+  fs,
+  sync::{ Mutex, MutexGuard, atomic::{ AtomicU64, Ordering } },
+  time::{ Duration, Instant },
+};
+use once_cell::sync::Lazy;
+
+use crate::helper::{ Edition, ServerStatus };
+
+/// Linux reports CPU times in clock ticks, which are 100 per second on every platform it supports in practice.
+const CLOCK_TICKS_PER_SECOND: f64 = 100.0;
+
+const STATUSES: [ServerStatus; 4] = [
+  ServerStatus::Sleeping,
+  ServerStatus::Starting,
+  ServerStatus::Running,
+  ServerStatus::Stopped,
+];
+
+const EDITIONS: [Edition; 3] = [Edition::Java, Edition::Bedrock, Edition::Web];
+
+/// Counters shared by the sleeping servers, the container and the Discord notifier, rendered on `/metrics`.
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// Why a connection was refused before reaching the sleeping servers.
+#[derive(Clone, Copy, Debug)]
+pub enum BlockReason {
+  Blacklist,
+  Whitelist,
+  /// In `banned-ips.json` or `banned-players.json`.
+  Banned,
+}
+
+impl BlockReason {
+  /// How the refused address or player is described in the logs.
+  pub fn describe(&self) -> &'static str {
+    match self {
+      BlockReason::Blacklist => "blacklisted",
+      BlockReason::Whitelist => "not whitelisted",
+      BlockReason::Banned => "banned",
+    }
+  }
+}
+
+/// How long the server spent in each status.
+struct StatusTimes {
+  current: ServerStatus,
+  since: Instant,
+  totals: [Duration; STATUSES.len()],
+}
+
+pub struct Metrics {
+  status_pings: [AtomicU64; EDITIONS.len()],
+  wake_attempts: [AtomicU64; EDITIONS.len()],
+  blocked_by_blacklist: AtomicU64,
+  blocked_by_whitelist: AtomicU64,
+  blocked_by_ban: AtomicU64,
+  discord_failures: AtomicU64,
+  status_times: Mutex<StatusTimes>,
+}
+
+impl Metrics {
+  fn new() -> Self {
+    Self {
+      status_pings: Default::default(),
+      wake_attempts: Default::default(),
+      blocked_by_blacklist: AtomicU64::new(0),
+      blocked_by_whitelist: AtomicU64::new(0),
+      blocked_by_ban: AtomicU64::new(0),
+      discord_failures: AtomicU64::new(0),
+      status_times: Mutex::new(StatusTimes {
+        current: ServerStatus::Sleeping,
+        since: Instant::now(),
+        totals: [Duration::ZERO; STATUSES.len()],
+      }),
+    }
+  }
+
+  /// A sleeping server answered a server list ping.
+  pub fn status_ping(&self, edition: Edition) {
+    self.status_pings[edition_index(edition)].fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn wake_attempt(&self, edition: Edition) {
+    self.wake_attempts[edition_index(edition)].fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn blocked_connection(&self, reason: BlockReason) {
+    match reason {
+      BlockReason::Blacklist => self.blocked_by_blacklist.fetch_add(1, Ordering::Relaxed),
+      BlockReason::Whitelist => self.blocked_by_whitelist.fetch_add(1, Ordering::Relaxed),
+      BlockReason::Banned => self.blocked_by_ban.fetch_add(1, Ordering::Relaxed),
+    };
+  }
+
+  pub fn discord_failure(&self) {
+    self.discord_failures.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Starts counting the time spent in `status`.
+  pub fn set_status(&self, status: ServerStatus) {
+    let mut status_times: MutexGuard<StatusTimes> = self.status_times.lock().unwrap();
+    let elapsed: Duration = status_times.since.elapsed();
+    let current: usize = status_index(status_times.current);
+    status_times.totals[current] += elapsed;
+    status_times.current = status;
+    status_times.since = Instant::now();
+  }
+
+  /// Prometheus text format of every metric. `online_players` and `minecraft_pid` come from the running
+  /// Minecraft server, if any.
+  pub fn render(&self, online_players: usize, minecraft_pid: Option<u32>) -> String {
+    let mut output: String = String::new();
+
+    header(&mut output, "crabslumber_status_pings_total", "counter", "Server list pings answered while sleeping.");
+    for edition in EDITIONS.iter().filter(|edition| **edition != Edition::Web) {
+      let count: u64 = self.status_pings[edition_index(*edition)].load(Ordering::Relaxed);
+      let _ = writeln!(output, "crabslumber_status_pings_total{{edition=\"{}\"}} {}", edition_label(*edition), count);
+    }
+
+    header(&mut output, "crabslumber_wake_attempts_total", "counter", "Attempts to wake the server up.");
+    for edition in EDITIONS {
+      let count: u64 = self.wake_attempts[edition_index(edition)].load(Ordering::Relaxed);
+      let _ = writeln!(output, "crabslumber_wake_attempts_total{{edition=\"{}\"}} {}", edition_label(edition), count);
+    }
+
+    header(&mut output, "crabslumber_blocked_connections_total", "counter", "Connections refused by the access lists.");
+    let _ = writeln!(
+      output,
+      "crabslumber_blocked_connections_total{{reason=\"blacklist\"}} {}",
+      self.blocked_by_blacklist.load(Ordering::Relaxed)
+    );
+    let _ = writeln!(
+      output,
+      "crabslumber_blocked_connections_total{{reason=\"whitelist\"}} {}",
+      self.blocked_by_whitelist.load(Ordering::Relaxed)
+    );
+    let _ = writeln!(
+      output,
+      "crabslumber_blocked_connections_total{{reason=\"banned\"}} {}",
+      self.blocked_by_ban.load(Ordering::Relaxed)
+    );
+
+    let status_times: MutexGuard<StatusTimes> = self.status_times.lock().unwrap();
+    header(&mut output, "crabslumber_status", "gauge", "Current server status, 1 for the current one.");
+    for status in STATUSES {
+      let value: u8 = if status == status_times.current { 1 } else { 0 };
+      let _ = writeln!(output, "crabslumber_status{{status=\"{}\"}} {}", status_label(status), value);
+    }
+
+    header(&mut output, "crabslumber_status_seconds_total", "counter", "Time spent in each status.");
+    for status in STATUSES {
+      let mut total: Duration = status_times.totals[status_index(status)];
+      if status == status_times.current {
+        total += status_times.since.elapsed();
+      }
+      let _ = writeln!(
+        output,
+        "crabslumber_status_seconds_total{{status=\"{}\"}} {:.3}",
+        status_label(status),
+        total.as_secs_f64()
+      );
+    }
+    drop(status_times);
+
+    header(&mut output, "crabslumber_online_players", "gauge", "Players on the Minecraft server.");
+    let _ = writeln!(output, "crabslumber_online_players {}", online_players);
+
+    header(&mut output, "crabslumber_discord_webhook_failures_total", "counter", "Discord messages that failed to send.");
+    let _ = writeln!(output, "crabslumber_discord_webhook_failures_total {}", self.discord_failures.load(Ordering::Relaxed));
+
+    // Only the process started by minecraftCommand, not the ones it starts itself
+    if let Some((resident_memory, cpu_seconds)) = minecraft_pid.and_then(process_usage) {
+      header(&mut output, "crabslumber_minecraft_resident_memory_bytes", "gauge", "Resident memory of Minecraft.");
+      let _ = writeln!(output, "crabslumber_minecraft_resident_memory_bytes {}", resident_memory);
+      header(&mut output, "crabslumber_minecraft_cpu_seconds_total", "counter", "CPU time of the current Minecraft run.");
+      let _ = writeln!(output, "crabslumber_minecraft_cpu_seconds_total {:.2}", cpu_seconds);
+    }
+
+    output
+  }
+}
+
+fn header(output: &mut String, name: &str, metric_type: &str, help: &str) {
+  let _ = writeln!(output, "# HELP {} {}", name, help);
+  let _ = writeln!(output, "# TYPE {} {}", name, metric_type);
+}
+
+fn edition_index(edition: Edition) -> usize {
+  EDITIONS.iter().position(|known| *known == edition).unwrap_or(0)
+}
+
+fn status_index(status: ServerStatus) -> usize {
+  STATUSES.iter().position(|known| *known == status).unwrap_or(0)
+}
+
+fn edition_label(edition: Edition) -> &'static str {
+  match edition {
+    Edition::Java => "java",
+    Edition::Bedrock => "bedrock",
+    Edition::Web => "web",
+  }
+}
+
+fn status_label(status: ServerStatus) -> &'static str {
+  match status {
+    ServerStatus::Sleeping => "sleeping",
+    ServerStatus::Starting => "starting",
+    ServerStatus::Running => "running",
+    ServerStatus::Stopped => "stopped",
+  }
+}
+
+/// Resident memory in bytes and CPU seconds of a process, from `/proc`. `None` if it is gone or this isn't Linux.
+fn process_usage(pid: u32) -> Option<(u64, f64)> {
+  let status: String = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+  let resident_kilobytes: u64 = status
+    .lines()
+    .find_map(|line| line.strip_prefix("VmRSS:"))?
+    .trim()
+    .trim_end_matches("kB")
+    .trim()
+    .parse()
+    .ok()?;
+
+  // The name in parentheses can contain spaces, the fields are counted from the end of it
+  let stat: String = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+  let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+  let user_ticks: u64 = fields.get(11)?.parse().ok()?;
+  let system_ticks: u64 = fields.get(12)?.parse().ok()?;
+
+  Some((resident_kilobytes * 1024, ((user_ticks + system_ticks) as f64) / CLOCK_TICKS_PER_SECOND))
+}
@@ -41,8 +41,8 @@ impl Extras {
   /// The function `get_from_symbol` returns a string slice (`&'static str`). If the input `symbol`
   /// matches one of the predefined strings, it returns the corresponding value from the `Extras` enum
   /// using the `get()` method. Otherwise, it returns an empty string (`""`).
-  pub fn get_from_symbol(symbol: &String) -> &'static str {
-    match symbol.as_str() {
+  pub fn get_from_symbol(symbol: &str) -> &'static str {
+    match symbol {
       "§k" => Extras::K.get(),
       "§l" => Extras::L.get(),
       "§m" => Extras::M.get(),
@@ -66,8 +66,8 @@ impl Extras {
   /// parameter with certain strings and returns the corresponding value from the `Extras` enum using
   /// the `get()` method. If the input `code` does not match any of the cases, an empty string is
   /// returned.
-  pub fn get_from_code(code: &String) -> &'static str {
-    match code.as_str() {
+  pub fn get_from_code(code: &str) -> &'static str {
+    match code {
       "k" => Extras::K.get(),
       "l" => Extras::L.get(),
       "m" => Extras::M.get(),
@@ -130,8 +130,8 @@ impl CodeToHex {
   /// This function returns a string slice (`&'static str`) representing the hexadecimal color code
   /// associated with the input Minecraft formatting code symbol. If the input symbol is not recognized,
   /// it returns the hexadecimal color code for white.
-  pub fn get_from_symbol(symbol: &String) -> &'static str {
-    match symbol.as_str() {
+  pub fn get_from_symbol(symbol: &str) -> &'static str {
+    match symbol {
       "§0" => CodeToHex::Black.get(),
       "§1" => CodeToHex::DarkBlue.get(),
       "§2" => CodeToHex::DarkGreen.get(),
@@ -187,12 +187,12 @@ impl CodeToHex {
   }
 }
 
-pub fn clean_tags(text: &String) -> String {
+pub fn clean_tags(text: &str) -> String {
   let regex = Regex::new(r"(?:§)([0-9a-fA-FklmnorFKLMNOR])").unwrap();
   regex.replace_all(text, "").to_string()
 }
 
-fn html_string_formatting(text: &String) -> String {
+fn html_string_formatting(text: &str) -> String {
   text.replace("&", "&amp;")
       .replace("<", "&lt;")
       .replace(">", "&gt;")
@@ -201,37 +201,47 @@ fn html_string_formatting(text: &String) -> String {
       .replace("\n", "<br/>")
 }
 
-pub fn text_to_html(text: &String) -> String {
+pub fn text_to_html(text: &str) -> String {
   let mut result_html = String::new();
   let mut color_hex = String::new();
   let mut font_style = String::new();
 
-  let regex = Regex::new(r"(?:§)([0-9a-fA-FklmnorFKLMNOR])").unwrap();
-  let code_split = regex.split(text);
-
-  for item in code_split {
-    let item_lowercase = item.to_lowercase();
-
-    if item_lowercase.len() == 1 {
-      color_hex = CodeToHex::get_from_symbol(&item_lowercase).to_string();
-    } else if item_lowercase.len() == 2 {
-      font_style = Extras::get_from_symbol(&item_lowercase).to_string();
-    } else {
+  // `Regex::split` drops the codes, the text is cut by hand around them to keep them
+  let regex = Regex::new(r"§[0-9a-fA-FklmnorFKLMNOR]").unwrap();
+  let mut code_split: Vec<(bool, &str)> = Vec::new();
+  let mut last_end = 0;
+  for code in regex.find_iter(text) {
+    code_split.push((false, &text[last_end..code.start()]));
+    code_split.push((true, code.as_str()));
+    last_end = code.end();
+  }
+  code_split.push((false, &text[last_end..]));
+
+  for (is_code, item) in code_split {
+    if is_code {
+      let item_lowercase = item.to_lowercase();
+      if item_lowercase == "§r" {
+        color_hex.clear();
+        font_style.clear();
+      } else if Extras::get_from_symbol(&item_lowercase).is_empty() {
+        // Like in game, a color resets the formatting
+        color_hex = CodeToHex::get_from_symbol(&item_lowercase).to_string();
+        font_style.clear();
+      } else {
+        font_style += Extras::get_from_symbol(&item_lowercase);
+      }
+    } else if !item.is_empty() {
       let mut result_color = String::new();
-      let mut text_content = item.to_string();
+      let text_content = html_string_formatting(item);
 
-      if color_hex.len() != 0 {
+      if !color_hex.is_empty() {
         result_color = format!("color:{};", color_hex);
       }
 
-      if text_content.len() != 0 {
-        text_content = html_string_formatting(&text_content);
-
-        if result_color.len() != 0 || font_style.len() != 0 {
-          result_html += &format!("<span style=\"{}{}\">{}</span>", result_color, font_style, text_content);
-        } else {
-          result_html += &text_content;
-        }
+      if !result_color.is_empty() || !font_style.is_empty() {
+        result_html += &format!("<span style=\"{}{}\">{}</span>", result_color, font_style, text_content);
+      } else {
+        result_html += &text_content;
       }
     }
   }
@@ -239,50 +249,91 @@ pub fn text_to_html(text: &String) -> String {
   result_html
 }
 
-pub fn text_to_json(text: &String) -> Value {
-  let mut result_json: Value = json!({
-    "text": "",
-    "extra": []
-  });
-
-  let mut color_hex: String = String::new();
-  let mut font_style: String = String::new();
-
-  let regex: Regex = Regex::new(r"(?:§)([0-9a-fA-FklmnorFKLMNOR])").unwrap();
-  let code_split: regex::Split = regex.split(text);
+/// Name of a `§` color code in chat components, which every client version understands unlike hex colors.
+fn json_color(code: &str) -> Option<&'static str> {
+  match code {
+    "§0" => Some("black"),
+    "§1" => Some("dark_blue"),
+    "§2" => Some("dark_green"),
+    "§3" => Some("dark_aqua"),
+    "§4" => Some("dark_red"),
+    "§5" => Some("dark_purple"),
+    "§6" => Some("gold"),
+    "§7" => Some("gray"),
+    "§8" => Some("dark_gray"),
+    "§9" => Some("blue"),
+    "§a" => Some("green"),
+    "§b" => Some("aqua"),
+    "§c" => Some("red"),
+    "§d" => Some("light_purple"),
+    "§e" => Some("yellow"),
+    "§f" => Some("white"),
+    _ => None,
+  }
+}
 
-  for item in code_split {
-    let item_lowercase: String = item.to_lowercase();
+/// Chat component field of a `§` formatting code.
+fn json_style(code: &str) -> Option<&'static str> {
+  match code {
+    "§k" => Some("obfuscated"),
+    "§l" => Some("bold"),
+    "§m" => Some("strikethrough"),
+    "§n" => Some("underlined"),
+    "§o" => Some("italic"),
+    _ => None,
+  }
+}
 
-    if item_lowercase.len() == 1 {
-      color_hex = CodeToHex::get_from_symbol(&item_lowercase).to_string();
-    } else if item_lowercase.len() == 2 {
-      font_style = Extras::get_from_symbol(&item_lowercase).to_string();
-    } else {
+pub fn text_to_json(text: &str) -> Value {
+  let mut extra: Vec<Value> = Vec::new();
+  let mut color: Option<&'static str> = None;
+  let mut styles: Vec<&'static str> = Vec::new();
+
+  // Same tokenizer as `text_to_html`, each code applies to the text after it
+  let regex: Regex = Regex::new(r"§[0-9a-fA-FklmnorFKLMNOR]").unwrap();
+  let mut segments: Vec<(bool, &str)> = Vec::new();
+  let mut last_end: usize = 0;
+  for code in regex.find_iter(text) {
+    segments.push((false, &text[last_end..code.start()]));
+    segments.push((true, code.as_str()));
+    last_end = code.end();
+  }
+  segments.push((false, &text[last_end..]));
+
+  for (is_code, item) in segments {
+    if is_code {
+      let item_lowercase: String = item.to_lowercase();
+      if let Some(style) = json_style(&item_lowercase) {
+        if !styles.contains(&style) {
+          styles.push(style);
+        }
+      } else {
+        // Like in game, a color resets the formatting, and §r resets everything
+        color = json_color(&item_lowercase);
+        styles.clear();
+      }
+    } else if !item.is_empty() {
       let mut inner_json: Value = json!({
-        "text": "",
-        "extra": []
+        "text": item
       });
-
-      if font_style.len() != 0 {
-        inner_json[&font_style] = serde_json::Value::Bool(true);
+      if let Some(color) = color {
+        inner_json["color"] = Value::from(color);
       }
-
-      if item.len() != 0 {
-        inner_json["text"] = item.into();
-      }
-
-      if color_hex.len() != 0 {
-        inner_json["color"] = serde_json::Value::String(String::from(&color_hex));
-      }
-
-      if result_json["extra"].is_array() {
-        result_json["extra"].as_array_mut().unwrap().push(inner_json);
+      for style in &styles {
+        inner_json[*style] = Value::Bool(true);
       }
+      extra.push(inner_json);
     }
   }
 
-  result_json
+  // Clients refuse an empty `extra`
+  if extra.is_empty() {
+    return json!({ "text": "" });
+  }
+  json!({
+    "text": "",
+    "extra": extra
+  })
 }
 
 pub fn json_to_html(json: &Value) -> String {
@@ -300,21 +351,21 @@ pub fn json_to_html(json: &Value) -> String {
     } else {
       let mut inner_html = String::new();
 
-      if font_style.len() != 0 {
+      if !font_style.is_empty() {
         inner_html += &format!("<span style=\"{}\">", font_style);
       }
 
       if json[key].is_string() {
-        inner_html += &text_to_html(&json[key].as_str().unwrap().to_string());
+        inner_html += &text_to_html(json[key].as_str().unwrap());
       } else if json[key].is_number() {
         inner_html += &text_to_html(&json[key].as_u64().unwrap().to_string());
       }
 
-      if font_style.len() != 0 {
+      if !font_style.is_empty() {
         inner_html += "</span>";
       }
 
-      if color_hex.len() != 0 {
+      if !color_hex.is_empty() {
         inner_html = format!("<span style=\"{}\">{}</span>", color_hex, inner_html);
       }
 
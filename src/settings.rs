@@ -20,6 +20,79 @@ pub enum Version {
   None,
 }
 
+/// How addresses are masked in the logs when `hideIpInLogs` is enabled.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum IpRedaction {
+  /// Replaced by `[hidden]`.
+  Full,
+  /// Only the network part is kept, `192.168.x.x` or `2001:db8:1234:x:x:x:x:x`.
+  Partial,
+}
+
+/// Limits of `logs/latest.log` and its archives. It is archived every day regardless.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[allow(non_snake_case)]
+pub struct LogRotation {
+  /// Archive `latest.log` once it grows past this size.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub maxSizeMegabytes: Option<u64>,
+  /// How many archives are kept, the oldest are deleted first.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub maxFiles: Option<usize>,
+  /// Archives older than this are deleted.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub maxAgeDays: Option<u64>,
+}
+
+/// How the log lines are written, to the terminal and `logs/latest.log` alike.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum LogFormat {
+  /// Colored lines for humans.
+  #[default]
+  Text,
+  /// One JSON object per line with the timestamp, level, module, message and the structured fields (player,
+  /// address, status...), for log collectors like Loki.
+  Json,
+}
+
+/// What a web interface account is allowed to do.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum WebRole {
+  /// Can see the status page and `GET /api/status`.
+  ReadOnly,
+  /// Can also wake, stop and send commands to the server.
+  Admin,
+}
+
+/// Static token, sent as `Authorization: Bearer <token>`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebToken {
+  pub token: String,
+  pub role: WebRole,
+}
+
+/// Account logging in with a password, hashed with argon2 (see `--hash-password`).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[allow(non_snake_case)]
+pub struct WebUser {
+  pub username: String,
+  pub passwordHash: String,
+  pub role: WebRole,
+}
+
+/// Who can use the web interface. When it is not set, everyone can see the status and wake the server but nobody
+/// can do what needs an admin.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebAuth {
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub tokens: Vec<WebToken>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub users: Vec<WebUser>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[allow(non_snake_case)]
 pub struct Settings {
@@ -35,7 +108,11 @@ pub struct Settings {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub webServeDynmap: Option<WebServeDynmap>,
   #[serde(skip_serializing_if = "Option::is_none")]
+  pub webDynmapPort: Option<u16>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   pub webSubPath: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub webAuth: Option<WebAuth>,
   pub startMinecraft: bool,
   pub minecraftCommand: String,
   #[serde(skip_serializing_if = "Option::is_none")]
@@ -54,8 +131,23 @@ pub struct Settings {
   pub blackListAddress: Option<Vec<String>>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub whiteListedNames: Option<Vec<String>>,
+  /// Disconnect message for the players missing from `whiteListedNames`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub whiteListMessage: Option<String>,
+  /// Also apply the whitelist, ops and bans of the Minecraft server in `minecraftWorkingDirectory`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub syncMinecraftLists: Option<bool>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub hideIpInLogs: Option<bool>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ipRedaction: Option<IpRedaction>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub logRotation: Option<LogRotation>,
+  /// Ignored when `--log-format` is given.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub logFormat: Option<LogFormat>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub autoSleepMinutes: Option<u32>,
 }
 
 pub static DEFAULT_SETTINGS: Lazy<Settings> = Lazy::new(|| {
@@ -76,11 +168,19 @@ pub static DEFAULT_SETTINGS: Lazy<Settings> = Lazy::new(|| {
     discordWebhookUrl: None,
     blackListAddress: None,
     whiteListedNames: None,
+    whiteListMessage: None,
+    syncMinecraftLists: None,
     hideIpInLogs: None,
+    ipRedaction: None,
+    logRotation: None,
+    logFormat: None,
     bedrockPort: None,
     preventStop: None,
     webServeDynmap: None,
+    webDynmapPort: None,
     webSubPath: None,
+    webAuth: None,
+    autoSleepMinutes: None,
   }
 });
 
@@ -95,23 +195,23 @@ fn save_default() -> Result<(), ()> {
           match writer.write_all(settings.as_bytes()) {
             Ok(_) => {
               info!("Settings file created!");
-              return Result::Ok(());
+              Result::Ok(())
             }
             Err(e) => {
               error!("Failed to write to settings file: {}", e);
-              return Result::Err(());
+              Result::Err(())
             }
           }
         }
         Err(e) => {
           error!("Failed to create settings file: {}", e);
-          return Result::Err(());
+          Result::Err(())
         }
       }
     }
     Err(e) => {
       error!("Failed to serialize default settings: {}", e);
-      return Result::Err(());
+      Result::Err(())
     }
   }
 }
@@ -138,26 +238,26 @@ fn backup_settings(mut reader: BufReader<File>) -> Result<(), ()> {
       trace!("Backup file contents: {}", settings);
       match backup_file {
         Ok(mut backup_file) => {
-          match backup_file.write_all(&settings.as_bytes()) {
+          match backup_file.write_all(settings.as_bytes()) {
             Ok(_) => {
               info!("Settings file backed up!");
-              return Result::Ok(());
+              Result::Ok(())
             }
             Err(e) => {
               error!("Failed to write to settings backup file: {}", e);
-              return Result::Err(());
+              Result::Err(())
             }
           }
         }
         Err(e) => {
           error!("Failed to create settings backup file: {}", e);
-          return Result::Err(());
+          Result::Err(())
         }
       }
     }
     Err(e) => {
       error!("Failed to read settings file: {}", e);
-      return Result::Err(());
+      Result::Err(())
     }
   }
 }
@@ -184,7 +284,9 @@ pub fn get_settings() -> Settings {
             webPort: settings_from_files.webPort,
             webStopOnStart: settings_from_files.webStopOnStart,
             webServeDynmap: settings_from_files.webServeDynmap.or(settings.webServeDynmap),
+            webDynmapPort: settings_from_files.webDynmapPort.or(settings.webDynmapPort),
             webSubPath: settings_from_files.webSubPath.or(settings.webSubPath),
+            webAuth: settings_from_files.webAuth.or(settings.webAuth),
             startMinecraft: settings_from_files.startMinecraft,
             minecraftCommand: settings_from_files.minecraftCommand.clone(),
             preventStop: settings_from_files.preventStop.or(settings.preventStop),
@@ -197,7 +299,13 @@ pub fn get_settings() -> Settings {
             discordWebhookUrl: settings_from_files.discordWebhookUrl.or(settings.discordWebhookUrl),
             blackListAddress: settings_from_files.blackListAddress.or(settings.blackListAddress),
             whiteListedNames: settings_from_files.whiteListedNames.or(settings.whiteListedNames),
+            whiteListMessage: settings_from_files.whiteListMessage.or(settings.whiteListMessage),
+            syncMinecraftLists: settings_from_files.syncMinecraftLists.or(settings.syncMinecraftLists),
             hideIpInLogs: settings_from_files.hideIpInLogs.or(settings.hideIpInLogs),
+            ipRedaction: settings_from_files.ipRedaction.or(settings.ipRedaction),
+            logRotation: settings_from_files.logRotation.or(settings.logRotation),
+            logFormat: settings_from_files.logFormat.or(settings.logFormat),
+            autoSleepMinutes: settings_from_files.autoSleepMinutes.or(settings.autoSleepMinutes),
           };
         }
         Err(e) => {
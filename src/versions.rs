@@ -0,0 +1,93 @@
+/// A Minecraft Java Edition release and the protocol number its clients send in the handshake.
+pub struct MinecraftVersion {
+  pub name: &'static str,
+  pub protocol: i32,
+}
+
+/// Known Java Edition releases, newest first. Some releases share a protocol number, in that case the
+/// newest one is the first to be found.
+pub const JAVA_VERSIONS: &[MinecraftVersion] = &[
+  MinecraftVersion { name: "1.20.1", protocol: 763 },
+  MinecraftVersion { name: "1.20", protocol: 763 },
+  MinecraftVersion { name: "1.19.4", protocol: 762 },
+  MinecraftVersion { name: "1.19.3", protocol: 761 },
+  MinecraftVersion { name: "1.19.2", protocol: 760 },
+  MinecraftVersion { name: "1.19.1", protocol: 760 },
+  MinecraftVersion { name: "1.19", protocol: 759 },
+  MinecraftVersion { name: "1.18.2", protocol: 758 },
+  MinecraftVersion { name: "1.18.1", protocol: 757 },
+  MinecraftVersion { name: "1.18", protocol: 757 },
+  MinecraftVersion { name: "1.17.1", protocol: 756 },
+  MinecraftVersion { name: "1.17", protocol: 755 },
+  MinecraftVersion { name: "1.16.5", protocol: 754 },
+  MinecraftVersion { name: "1.16.4", protocol: 754 },
+  MinecraftVersion { name: "1.16.3", protocol: 753 },
+  MinecraftVersion { name: "1.16.2", protocol: 751 },
+  MinecraftVersion { name: "1.16.1", protocol: 736 },
+  MinecraftVersion { name: "1.16", protocol: 735 },
+  MinecraftVersion { name: "1.15.2", protocol: 578 },
+  MinecraftVersion { name: "1.15.1", protocol: 575 },
+  MinecraftVersion { name: "1.15", protocol: 573 },
+  MinecraftVersion { name: "1.14.4", protocol: 498 },
+  MinecraftVersion { name: "1.14.3", protocol: 490 },
+  MinecraftVersion { name: "1.14.2", protocol: 485 },
+  MinecraftVersion { name: "1.14.1", protocol: 480 },
+  MinecraftVersion { name: "1.14", protocol: 477 },
+  MinecraftVersion { name: "1.13.2", protocol: 404 },
+  MinecraftVersion { name: "1.13.1", protocol: 401 },
+  MinecraftVersion { name: "1.13", protocol: 393 },
+  MinecraftVersion { name: "1.12.2", protocol: 340 },
+  MinecraftVersion { name: "1.12.1", protocol: 338 },
+  MinecraftVersion { name: "1.12", protocol: 335 },
+  MinecraftVersion { name: "1.11.2", protocol: 316 },
+  MinecraftVersion { name: "1.11.1", protocol: 316 },
+  MinecraftVersion { name: "1.11", protocol: 315 },
+  MinecraftVersion { name: "1.10.2", protocol: 210 },
+  MinecraftVersion { name: "1.10.1", protocol: 210 },
+  MinecraftVersion { name: "1.10", protocol: 210 },
+  MinecraftVersion { name: "1.9.4", protocol: 110 },
+  MinecraftVersion { name: "1.9.3", protocol: 110 },
+  MinecraftVersion { name: "1.9.2", protocol: 109 },
+  MinecraftVersion { name: "1.9.1", protocol: 108 },
+  MinecraftVersion { name: "1.9", protocol: 107 },
+  MinecraftVersion { name: "1.8.9", protocol: 47 },
+  MinecraftVersion { name: "1.8.8", protocol: 47 },
+  MinecraftVersion { name: "1.8.7", protocol: 47 },
+  MinecraftVersion { name: "1.8.6", protocol: 47 },
+  MinecraftVersion { name: "1.8.5", protocol: 47 },
+  MinecraftVersion { name: "1.8.4", protocol: 47 },
+  MinecraftVersion { name: "1.8.3", protocol: 47 },
+  MinecraftVersion { name: "1.8.2", protocol: 47 },
+  MinecraftVersion { name: "1.8.1", protocol: 47 },
+  MinecraftVersion { name: "1.8", protocol: 47 },
+  MinecraftVersion { name: "1.7.10", protocol: 5 },
+  MinecraftVersion { name: "1.7.9", protocol: 5 },
+  MinecraftVersion { name: "1.7.8", protocol: 5 },
+  MinecraftVersion { name: "1.7.7", protocol: 5 },
+  MinecraftVersion { name: "1.7.6", protocol: 5 },
+  MinecraftVersion { name: "1.7.5", protocol: 4 },
+  MinecraftVersion { name: "1.7.4", protocol: 4 },
+  MinecraftVersion { name: "1.7.2", protocol: 4 },
+];
+
+/// Returns the protocol number of a release name, like `763` for `"1.20"`.
+pub fn get_protocol(name: &str) -> Option<i32> {
+  JAVA_VERSIONS.iter()
+    .find(|version| version.name == name.trim())
+    .map(|version| version.protocol)
+}
+
+/// Returns the newest release name using the given protocol number, like `"1.20.1"` for `763`.
+pub fn get_version_name(protocol: i32) -> Option<&'static str> {
+  JAVA_VERSIONS.iter()
+    .find(|version| version.protocol == protocol)
+    .map(|version| version.name)
+}
+
+/// Human readable description of a client protocol, used for logging.
+pub fn describe_protocol(protocol: i32) -> String {
+  match get_version_name(protocol) {
+    Some(name) => format!("{} (protocol {})", name, protocol),
+    None => format!("unknown version (protocol {})", protocol),
+  }
+}
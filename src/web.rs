@@ -0,0 +1,949 @@
+use std::{ convert::Infallible, io, net::SocketAddr, path::PathBuf, sync::{ Arc, Weak } };
+use simplelog::{ error, info, debug, warn, LevelFilter };
+use serde::Deserialize;
+use serde_json::{ Value, json };
+use axum::{
+  Form,
+  Json,
+  Router,
+  async_trait,
+  body::{ Body, boxed },
+  extract::{ ConnectInfo, FromRequestParts, OriginalUri, State, ws::{ Message, WebSocket, WebSocketUpgrade } },
+  http::{ Request, StatusCode, HeaderMap, HeaderValue, Uri, header, request::Parts },
+  middleware::{ self, Next },
+  response::{ Html, IntoResponse, Redirect, Response, sse::{ Event, KeepAlive, Sse } },
+  routing::{ any, get, post },
+};
+use futures_util::stream;
+use hyper::client::{ Client, HttpConnector };
+use tower::ServiceExt;
+use tower_http::services::ServeDir;
+use tokio::{ net::TcpListener, sync::broadcast::{ Receiver, error::RecvError }, task::JoinHandle };
+use tokio_util::{ sync::CancellationToken, task::TaskTracker };
+
+use crate::auth::{ Authentication, Identity, WebAuthenticator, SESSION_COOKIE, SESSION_DURATION };
+use crate::constants::{ ADDRESS, DYNMAP_PORT };
+use crate::container::{ Console, MinecraftProcess, SleepingContainer };
+use crate::events::ServerEvent;
+use crate::logger::{ get_log_level, log_fields, log_level_command, set_log_level, LogOutput };
+use crate::helper::{ display_address, get_motd, get_fav_icon, MotdOutput, MotdOutputType, Edition, Player, ServerStatus };
+use crate::metrics::{ BlockReason, METRICS };
+use crate::motd_parser::{ clean_tags, text_to_html, text_to_json };
+use crate::settings::{ Settings, WebRole, WebServeDynmap };
+
+/// Status page, `{...}` placeholders are replaced by `status_page`. The status is polled from the API and the
+/// console streamed over a WebSocket, so both stay live.
+const STATUS_PAGE_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>{title}</title>
+    <link rel="icon" href="{favicon}">
+    <style>
+      body { background: #1e1e1e; color: #aaaaaa; font-family: monospace; display: flex; justify-content: center; }
+      main { margin-top: 10vh; padding: 1.5em; background: #2b2b2b; border-radius: 0.5em; text-align: center; }
+      img { width: 64px; height: 64px; image-rendering: pixelated; }
+      .motd { font-size: 1.3em; margin: 0.5em 0; }
+      .status { font-weight: bold; }
+      .status-sleeping { color: #5555ff; }
+      .status-starting { color: #ffaa00; }
+      .status-running { color: #55ff55; }
+      .status-stopped { color: #ff5555; }
+      button { margin-top: 1em; padding: 0.5em 2em; font-family: inherit; font-size: 1.1em; cursor: pointer; }
+      button:disabled { cursor: default; }
+      .account { margin-top: 1.5em; font-size: 0.9em; }
+      .account button { margin: 0 0 0 0.5em; padding: 0.1em 0.5em; font-size: 1em; }
+      .console { margin-top: 1.5em; width: 80ch; max-width: 90vw; text-align: left; }
+      .console pre { height: 20em; margin: 0; padding: 0.5em; overflow-y: auto; background: #1e1e1e; white-space: pre-wrap; }
+      .console input { box-sizing: border-box; width: 100%; margin-top: 0.5em; padding: 0.4em; font-family: inherit; }
+    </style>
+  </head>
+  <body>
+    <main>
+      <img src="{favicon}" alt="Server icon">
+      <div class="motd">{motd}</div>
+      <div>Status: <span id="status" class="status status-{status_class}">{status}</span></div>
+      <form method="post" action="{base_path}/wakeup">
+        <button id="wake" type="submit"{disabled}>Wake up</button>
+      </form>
+      <div class="console">
+        <pre id="console"></pre>
+        {command_input}
+      </div>
+      {account}
+    </main>
+    <script>
+      const canWake = {can_wake};
+      const status = document.getElementById("status");
+      const wake = document.getElementById("wake");
+      const output = document.getElementById("console");
+      const input = document.getElementById("command");
+
+      async function refreshStatus() {
+        try {
+          const response = await fetch("{base_path}/api/status");
+          if (!response.ok) return;
+          const name = (await response.json()).status;
+          status.textContent = name;
+          status.className = "status status-" + name.toLowerCase();
+          wake.disabled = !canWake || name !== "Sleeping";
+        } catch (e) {}
+      }
+      setInterval(refreshStatus, 5000);
+
+      function print(line) {
+        const atBottom = output.scrollTop + output.clientHeight >= output.scrollHeight - 5;
+        output.append(line + "\n");
+        if (atBottom) output.scrollTop = output.scrollHeight;
+      }
+
+      function connect() {
+        const scheme = location.protocol === "https:" ? "wss://" : "ws://";
+        const socket = new WebSocket(scheme + location.host + "{base_path}/api/console");
+        socket.onopen = () => { output.textContent = ""; };
+        socket.onmessage = (event) => print(event.data);
+        socket.onclose = () => setTimeout(connect, 5000);
+        if (input) {
+          input.onkeydown = (event) => {
+            if (event.key !== "Enter" || !input.value.trim() || socket.readyState !== WebSocket.OPEN) return;
+            socket.send(input.value);
+            input.value = "";
+          };
+        }
+      }
+      connect();
+    </script>
+  </body>
+</html>
+"#;
+
+/// Login form, shown instead of the status page when `webAuth` is set.
+const LOGIN_PAGE_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>{title}</title>
+    <link rel="icon" href="{favicon}">
+    <style>
+      body { background: #1e1e1e; color: #aaaaaa; font-family: monospace; display: flex; justify-content: center; }
+      main { margin-top: 10vh; padding: 1.5em; background: #2b2b2b; border-radius: 0.5em; text-align: center; }
+      input, button { display: block; margin: 0.5em auto; padding: 0.4em; font-family: inherit; font-size: 1em; }
+      .error { color: #ff5555; }
+    </style>
+  </head>
+  <body>
+    <main>
+      <form method="post" action="{base_path}/login">
+        <input name="username" placeholder="Username" autocomplete="username" required autofocus>
+        <input name="password" type="password" placeholder="Password" autocomplete="current-password" required>
+        <button type="submit">Log in</button>
+      </form>
+      <div class="error">{error}</div>
+    </main>
+  </body>
+</html>
+"#;
+
+/// Name given to whoever wakes the server from the web interface, shown in the logs and Discord messages.
+const WEB_PLAYER_NAME: &str = "A web user";
+
+/// Answer of the JSON API, a status code and a body.
+type ApiResponse = (StatusCode, Json<Value>);
+
+#[derive(Deserialize)]
+struct CommandRequest {
+  command: String,
+}
+
+/// Levels like `trace` or `off`, outputs left out keep theirs.
+#[derive(Deserialize)]
+struct LogLevelRequest {
+  terminal: Option<String>,
+  file: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+  username: String,
+  password: String,
+}
+
+struct WebState {
+  settings: Settings,
+  container: Weak<SleepingContainer>,
+  base_path: String,
+  auth: WebAuthenticator,
+  /// Exported Dynmap files, served while Minecraft doesn't run.
+  dynmap_files: Option<ServeDir>,
+  /// Port of the Dynmap web server, proxied while Minecraft runs.
+  dynmap_port: Option<u16>,
+  http_client: Client<HttpConnector>,
+  /// Cancelled when the web server closes. The graceful shutdown waits for the responses in progress, so the
+  /// console and event streams have to end by themselves.
+  closing: CancellationToken,
+}
+
+impl WebState {
+  fn get_status(&self) -> ServerStatus {
+    match self.container.upgrade() {
+      Some(container) => container.get_status(),
+      None => ServerStatus::Stopped,
+    }
+  }
+
+  /// Role needed to wake the server. Without `webAuth` nobody is an admin, so anyone can wake it.
+  fn wake_role(&self) -> WebRole {
+    if self.auth.is_enabled() {
+      WebRole::Admin
+    } else {
+      WebRole::ReadOnly
+    }
+  }
+
+  /// Checks that the request comes from someone with at least `role`. Pages send unknown visitors to the login
+  /// form, the API answers with a JSON error.
+  async fn authorize(&self, parts: &Parts, role: WebRole) -> Result<Identity, Response> {
+    let authorization: Option<&str> = parts.headers.get(header::AUTHORIZATION).and_then(|value| value.to_str().ok());
+    let cookie: Option<&str> = parts.headers.get(header::COOKIE).and_then(|value| value.to_str().ok());
+    // Prometheus can't follow the login form, it gets the API errors too
+    let is_api: bool = parts.uri.path().starts_with("/api/") || parts.uri.path() == "/metrics";
+
+    match self.auth.authenticate(authorization, cookie).await {
+      Authentication::Authenticated(identity) if identity.role >= role => Ok(identity),
+      Authentication::Authenticated(identity) => {
+        warn!("[Web] {} is not allowed to {} {}", identity.name, parts.method, parts.uri.path());
+        let message: &str = if self.auth.is_enabled() {
+          "Only admins can do this"
+        } else {
+          "Only admins can do this, set webAuth to add some"
+        };
+        if is_api {
+          Err(api_error(StatusCode::FORBIDDEN, message).into_response())
+        } else {
+          Err((StatusCode::FORBIDDEN, message).into_response())
+        }
+      }
+      authentication => {
+        if let Authentication::Failed(name) = authentication {
+          let peer: Option<SocketAddr> = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|info| info.0);
+          self.log_failed_attempt(&name, peer);
+        }
+        if is_api {
+          let mut response: Response = api_error(StatusCode::UNAUTHORIZED, "Authentication required").into_response();
+          response.headers_mut().insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+          Err(response)
+        } else {
+          Err(Redirect::to(&format!("{}/login", self.base_path)).into_response())
+        }
+      }
+    }
+  }
+
+  /// Whether the sender of a wake up request is blacklisted or banned, logging the refusal if so.
+  fn is_refused(&self, peer: &SocketAddr) -> bool {
+    let refusal: Option<BlockReason> = self.container
+      .upgrade()
+      .and_then(|container| container.get_access().check_address(&peer.ip()).err());
+    match refusal {
+      Some(reason) => {
+        let address: String = display_address(&self.settings, &peer.ip());
+        log_fields!(
+          info, address = address.as_str(), reason = reason.describe();
+          "[Web] Refused wake up from {} address {}", reason.describe(), address
+        );
+        METRICS.blocked_connection(reason);
+        true
+      }
+      None => false,
+    }
+  }
+
+  fn log_failed_attempt(&self, name: &str, peer: Option<SocketAddr>) {
+    let address: String = match peer {
+      Some(peer) => display_address(&self.settings, &peer.ip()),
+      None => String::from("an unknown address"),
+    };
+    log_fields!(
+      warn, user = name, address = address.as_str();
+      "[Web] Failed authentication as {} from {}", name, address
+    );
+  }
+}
+
+/// Requests from anyone allowed to see the status.
+struct ReadAccess(Identity);
+
+/// Requests from someone allowed to wake the server.
+struct WakeAccess(Identity);
+
+/// Requests from an admin, allowed to change the server state.
+struct AdminAccess(Identity);
+
+#[async_trait]
+impl FromRequestParts<Arc<WebState>> for ReadAccess {
+  type Rejection = Response;
+
+  async fn from_request_parts(parts: &mut Parts, state: &Arc<WebState>) -> Result<Self, Self::Rejection> {
+    state.authorize(parts, WebRole::ReadOnly).await.map(Self)
+  }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<WebState>> for WakeAccess {
+  type Rejection = Response;
+
+  async fn from_request_parts(parts: &mut Parts, state: &Arc<WebState>) -> Result<Self, Self::Rejection> {
+    state.authorize(parts, state.wake_role()).await.map(Self)
+  }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<WebState>> for AdminAccess {
+  type Rejection = Response;
+
+  async fn from_request_parts(parts: &mut Parts, state: &Arc<WebState>) -> Result<Self, Self::Rejection> {
+    state.authorize(parts, WebRole::Admin).await.map(Self)
+  }
+}
+
+/// Web interface showing the server status and letting people without Minecraft wake it up.
+pub struct SleepingWeb {
+  settings: Settings,
+  container: Weak<SleepingContainer>,
+  tasks: TaskTracker,
+  closing: CancellationToken,
+  handle: Option<JoinHandle<()>>,
+}
+
+impl SleepingWeb {
+  /// `shutdown` is the container's token, cancelling it also closes the web server.
+  pub fn new(
+    settings: Settings,
+    container: Weak<SleepingContainer>,
+    tasks: TaskTracker,
+    shutdown: &CancellationToken
+  ) -> Self {
+    Self {
+      settings,
+      container,
+      tasks,
+      closing: shutdown.child_token(),
+      handle: None,
+    }
+  }
+
+  /// Binds the web server on `webPort` and serves it from a background task.
+  pub async fn init(&mut self) -> io::Result<()> {
+    let address: String = format!("{}:{}", ADDRESS, self.settings.webPort);
+    let listener: TcpListener = TcpListener::bind(&address).await?;
+    let base_path: String = get_base_path(&self.settings);
+    info!("[Web] Web interface listening on http://{}{}/", address, base_path);
+    if self.settings.webAuth.is_none() {
+      warn!("[Web] webAuth is not set, anyone can see the status and wake the server and admin actions are disabled");
+    }
+
+    let dynmap_path: Option<PathBuf> = get_dynmap_path(&self.settings);
+    let dynmap_port: Option<u16> = self.settings.webDynmapPort.or(dynmap_path.as_ref().map(|_| DYNMAP_PORT));
+    if let Some(dynmap_path) = &dynmap_path {
+      if !dynmap_path.is_dir() {
+        warn!("[Web] Dynmap web folder {} does not exist", dynmap_path.display());
+      }
+      info!("[Web] Serving Dynmap from {} on {}/map/ while Minecraft is not running", dynmap_path.display(), base_path);
+    }
+    if let Some(dynmap_port) = dynmap_port {
+      info!("[Web] Proxying {}/map/ to Dynmap on port {} while Minecraft is running", base_path, dynmap_port);
+    }
+
+    let state: Arc<WebState> = Arc::new(WebState {
+      settings: self.settings.clone(),
+      container: Weak::clone(&self.container),
+      base_path: base_path.clone(),
+      auth: WebAuthenticator::new(self.settings.webAuth.clone()),
+      dynmap_files: dynmap_path.map(ServeDir::new),
+      dynmap_port,
+      http_client: Client::new(),
+      closing: self.closing.clone(),
+    });
+    let mut routes: Router<Arc<WebState>> = Router::new()
+      .route("/", get(status_page))
+      .route("/wakeup", post(wake_up))
+      .route("/login", get(login_page).post(login))
+      .route("/logout", post(logout))
+      .route("/api/status", get(api_status))
+      .route("/api/wake", post(api_wake))
+      .route("/api/stop", post(api_stop))
+      .route("/api/command", post(api_command))
+      .route("/api/log-level", get(api_log_level).post(api_set_log_level))
+      .route("/api/console", get(api_console))
+      .route("/api/events", get(api_events))
+      .route("/metrics", get(metrics));
+    if state.dynmap_files.is_some() || state.dynmap_port.is_some() {
+      routes = routes.merge(
+        Router::new()
+          .nest_service("/map", any(dynmap).with_state(Arc::clone(&state)))
+          .layer(middleware::from_fn(add_trailing_slash))
+          .layer(middleware::from_fn_with_state(Arc::clone(&state), require_read_access))
+      );
+    }
+    let router: Router = if base_path.is_empty() {
+      routes.with_state(state)
+    } else {
+      // Nesting only matches the prefix without the trailing slash, which the page links to
+      Router::new()
+        .route(&format!("{}/", base_path), get(status_page))
+        .nest(&base_path, routes)
+        .with_state(state)
+    };
+
+    let server = axum::Server::from_tcp(listener.into_std()?)
+      .map_err(io::Error::other)?
+      .serve(router.into_make_service_with_connect_info::<SocketAddr>());
+    let closing: CancellationToken = self.closing.clone();
+
+    self.handle = Some(
+      self.tasks.spawn(async move {
+        if let Err(e) = server.with_graceful_shutdown(closing.cancelled_owned()).await {
+          error!("[Web] Web server failed: {}", e);
+        }
+        // The listener is dropped here, releasing the port
+      })
+    );
+
+    Ok(())
+  }
+
+  /// Stops the web server and waits until the port is released.
+  pub async fn close(&mut self) {
+    self.closing.cancel();
+    if let Some(handle) = self.handle.take() {
+      if handle.await.is_err() {
+        error!("[Web] Web server task panicked");
+      }
+      info!("[Web] Web interface closed");
+    }
+  }
+}
+
+/// `webSubPath` as a route prefix: empty, or starting with a `/` and without a trailing one.
+fn get_base_path(settings: &Settings) -> String {
+  match &settings.webSubPath {
+    Some(sub_path) => {
+      let sub_path: &str = sub_path.trim().trim_matches('/');
+      if sub_path.is_empty() {
+        String::new()
+      } else {
+        format!("/{}", sub_path)
+      }
+    }
+    None => String::new(),
+  }
+}
+
+async fn status_page(ReadAccess(identity): ReadAccess, State(state): State<Arc<WebState>>) -> Html<String> {
+  let settings: &Settings = &state.settings;
+  let status: ServerStatus = state.get_status();
+  let motd: String = match get_motd(settings, MotdOutputType::HTML) {
+    Some(MotdOutput::HTML(motd)) => motd,
+    _ => text_to_html(&clean_tags(&settings.serverName)),
+  };
+  let status_name: String = format!("{:?}", status);
+  let is_admin: bool = state.auth.is_enabled() && identity.role == WebRole::Admin;
+  let is_allowed_to_wake: bool = identity.role >= state.wake_role();
+  let can_wake: bool = status == ServerStatus::Sleeping && is_allowed_to_wake;
+  let account: String = if state.auth.is_enabled() {
+    format!(
+      r#"<form class="account" method="post" action="{}/logout">Logged in as {}<button type="submit">Log out</button></form>"#,
+      state.base_path,
+      text_to_html(&identity.name)
+    )
+  } else {
+    String::new()
+  };
+
+  Html(
+    STATUS_PAGE_TEMPLATE.replace("{title}", &text_to_html(&clean_tags(&settings.serverName)))
+      .replace("{favicon}", &get_fav_icon(settings))
+      .replace("{motd}", &motd)
+      .replace("{status_class}", &status_name.to_lowercase())
+      .replace("{status}", &status_name)
+      .replace("{base_path}", &state.base_path)
+      .replace("{disabled}", if can_wake { "" } else { " disabled" })
+      .replace("{account}", &account)
+      .replace("{can_wake}", if is_allowed_to_wake { "true" } else { "false" })
+      .replace(
+        "{command_input}",
+        if is_admin { r#"<input id="command" placeholder="Console command" autocomplete="off">"# } else { "" }
+      )
+  )
+}
+
+async fn wake_up(
+  WakeAccess(identity): WakeAccess,
+  State(state): State<Arc<WebState>>,
+  ConnectInfo(peer): ConnectInfo<SocketAddr>
+) -> Response {
+  if state.is_refused(&peer) {
+    return (StatusCode::FORBIDDEN, "Your address is not allowed to wake the server").into_response();
+  }
+  match state.container.upgrade() {
+    Some(container) => {
+      info!("[Web] Wake up requested by {} from the web interface", identity.name);
+      container.wake_up(web_player());
+    }
+    None => debug!("[Web] Wake up requested while the container is gone"),
+  }
+  Redirect::to(&format!("{}/", state.base_path)).into_response()
+}
+
+fn login_page_html(state: &WebState, error: &str) -> Html<String> {
+  Html(
+    LOGIN_PAGE_TEMPLATE.replace("{title}", &text_to_html(&clean_tags(&state.settings.serverName)))
+      .replace("{favicon}", &get_fav_icon(&state.settings))
+      .replace("{base_path}", &state.base_path)
+      .replace("{error}", error)
+  )
+}
+
+async fn login_page(State(state): State<Arc<WebState>>) -> Response {
+  if !state.auth.is_enabled() {
+    return Redirect::to(&format!("{}/", state.base_path)).into_response();
+  }
+  login_page_html(&state, "").into_response()
+}
+
+async fn login(
+  State(state): State<Arc<WebState>>,
+  ConnectInfo(peer): ConnectInfo<SocketAddr>,
+  Form(form): Form<LoginForm>
+) -> Response {
+  match state.auth.login(&form.username, &form.password).await {
+    Some((session_id, identity)) => {
+      info!("[Web] {} logged in to the web interface", identity.name);
+      let cookie: String = format!(
+        "{}={}; Path={}/; Max-Age={}; HttpOnly; SameSite=Strict",
+        SESSION_COOKIE,
+        session_id,
+        state.base_path,
+        SESSION_DURATION.as_secs()
+      );
+      let mut response: Response = Redirect::to(&format!("{}/", state.base_path)).into_response();
+      if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+        response.headers_mut().insert(header::SET_COOKIE, cookie);
+      }
+      response
+    }
+    None => {
+      state.log_failed_attempt(&form.username, Some(peer));
+      (StatusCode::UNAUTHORIZED, login_page_html(&state, "Wrong username or password")).into_response()
+    }
+  }
+}
+
+async fn logout(State(state): State<Arc<WebState>>, headers: HeaderMap) -> Response {
+  state.auth.logout(headers.get(header::COOKIE).and_then(|value| value.to_str().ok()));
+  let cookie: String = format!("{}=; Path={}/; Max-Age=0; HttpOnly; SameSite=Strict", SESSION_COOKIE, state.base_path);
+  let mut response: Response = Redirect::to(&format!("{}/login", state.base_path)).into_response();
+  if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+    response.headers_mut().insert(header::SET_COOKIE, cookie);
+  }
+  response
+}
+
+/// Folder of the Dynmap static web files, according to `webServeDynmap`: `true` uses the default Dynmap folder
+/// in `minecraftWorkingDirectory`, a string is the path to the folder.
+fn get_dynmap_path(settings: &Settings) -> Option<PathBuf> {
+  match &settings.webServeDynmap {
+    Some(WebServeDynmap::Bool(true)) => {
+      let working_directory: PathBuf = PathBuf::from(settings.minecraftWorkingDirectory.as_deref().unwrap_or("."));
+      Some(working_directory.join("plugins").join("dynmap").join("web"))
+    }
+    Some(WebServeDynmap::String(path)) => Some(PathBuf::from(path)),
+    _ => None,
+  }
+}
+
+/// Lets the request through to the service behind it only if its sender can see the status.
+async fn require_read_access<B>(_: ReadAccess, request: Request<B>, next: Next<B>) -> Response {
+  next.run(request).await
+}
+
+/// Live map from the Dynmap web server while Minecraft runs, the exported files otherwise.
+async fn dynmap(State(state): State<Arc<WebState>>, request: Request<Body>) -> Response {
+  let (parts, body) = request.into_parts();
+
+  if let Some(port) = state.dynmap_port {
+    if state.get_status() == ServerStatus::Running {
+      match proxy(&state.http_client, port, &parts, body).await {
+        Ok(response) => {
+          return response;
+        }
+        // Dynmap starts a while after the server, the files are better than nothing until then
+        Err(e) => debug!("[Web] Dynmap is not reachable on port {}: {}", port, e),
+      }
+    }
+  }
+
+  match &state.dynmap_files {
+    Some(dynmap_files) => {
+      match dynmap_files.clone().oneshot(Request::from_parts(parts, Body::empty())).await {
+        Ok(response) => response.map(boxed),
+        Err(never) => match never {},
+      }
+    }
+    None => (StatusCode::SERVICE_UNAVAILABLE, "The map is only available while the server is running").into_response(),
+  }
+}
+
+/// Forwards a request to the Dynmap web server on `port`.
+async fn proxy(client: &Client<HttpConnector>, port: u16, parts: &Parts, body: Body) -> Result<Response, String> {
+  let path_and_query: &str = parts.uri.path_and_query().map(|path_and_query| path_and_query.as_str()).unwrap_or("/");
+  let authority: String = format!("127.0.0.1:{}", port);
+  let uri: Uri = Uri::builder()
+    .scheme("http")
+    .authority(authority.as_str())
+    .path_and_query(path_and_query)
+    .build()
+    .map_err(|e| e.to_string())?;
+
+  let mut request: Request<Body> = Request::new(body);
+  *request.method_mut() = parts.method.clone();
+  *request.uri_mut() = uri;
+  *request.headers_mut() = parts.headers.clone();
+  let headers: &mut HeaderMap = request.headers_mut();
+  remove_hop_by_hop_headers(headers);
+  // Our credentials are none of Dynmap's business
+  headers.remove(header::AUTHORIZATION);
+  let cookie: Option<String> = headers
+    .get(header::COOKIE)
+    .and_then(|cookie| cookie.to_str().ok())
+    .map(|cookie| without_cookie(cookie, SESSION_COOKIE));
+  headers.remove(header::COOKIE);
+  if let Some(cookie) = cookie.filter(|cookie| !cookie.is_empty()) {
+    headers.insert(header::COOKIE, HeaderValue::from_str(&cookie).map_err(|e| e.to_string())?);
+  }
+  headers.insert(header::HOST, HeaderValue::from_str(&authority).map_err(|e| e.to_string())?);
+
+  let mut response: Response = client
+    .request(request)
+    .await
+    .map(IntoResponse::into_response)
+    .map_err(|e| e.to_string())?;
+  remove_hop_by_hop_headers(response.headers_mut());
+  Ok(response)
+}
+
+/// Headers only meant for a single connection, which a proxy must not pass on. The headers named by `Connection`
+/// are part of them.
+fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+  let named: Vec<String> = headers
+    .get_all(header::CONNECTION)
+    .iter()
+    .filter_map(|value| value.to_str().ok())
+    .flat_map(|value| value.split(','))
+    .map(|name| name.trim().to_lowercase())
+    .filter(|name| !name.is_empty())
+    .collect();
+  for name in named {
+    headers.remove(name.as_str());
+  }
+  for name in [
+    header::CONNECTION,
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+  ] {
+    headers.remove(name);
+  }
+  headers.remove("keep-alive");
+}
+
+/// The `Cookie` header without the cookie called `name`.
+fn without_cookie(cookie: &str, name: &str) -> String {
+  cookie
+    .split(';')
+    .map(|pair| pair.trim())
+    .filter(|pair| !pair.is_empty() && pair.split('=').next().map(|key| key.trim()) != Some(name))
+    .collect::<Vec<&str>>()
+    .join("; ")
+}
+
+/// Redirects `/map` to `/map/`, otherwise the relative links of the map page would miss the folder.
+async fn add_trailing_slash<B>(OriginalUri(uri): OriginalUri, request: Request<B>, next: Next<B>) -> Response {
+  if request.uri().path() == "/map" {
+    return Redirect::permanent(&format!("{}/", uri.path())).into_response();
+  }
+  next.run(request).await
+}
+
+fn web_player() -> Player {
+  Player { name: String::from(WEB_PLAYER_NAME), edition: Edition::Web }
+}
+
+fn api_error(status: StatusCode, message: &str) -> ApiResponse {
+  (status, Json(json!({ "error": message })))
+}
+
+/// Error answered when an action needs Minecraft but it isn't running.
+fn not_running_error(e: io::Error) -> ApiResponse {
+  match e.kind() {
+    io::ErrorKind::NotConnected | io::ErrorKind::BrokenPipe => api_error(StatusCode::CONFLICT, &e.to_string()),
+    _ => {
+      error!("[Web] Failed to reach Minecraft: {}", e);
+      api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string())
+    }
+  }
+}
+
+fn gone_error() -> ApiResponse {
+  api_error(StatusCode::SERVICE_UNAVAILABLE, "Shutting down")
+}
+
+async fn api_status(_: ReadAccess, State(state): State<Arc<WebState>>) -> ApiResponse {
+  let settings: &Settings = &state.settings;
+  let minecraft: Option<Arc<MinecraftProcess>> = state.container.upgrade().and_then(|container| container.get_minecraft());
+  let players: Vec<String> = minecraft.as_ref().map(|minecraft| minecraft.get_players()).unwrap_or_default();
+  let motd: Value = match get_motd(settings, MotdOutputType::JSON) {
+    Some(MotdOutput::JSON(motd)) => motd,
+    _ => text_to_json(&settings.serverName),
+  };
+
+  (
+    StatusCode::OK,
+    Json(
+      json!({
+        "status": format!("{:?}", state.get_status()),
+        "players": {
+          "online": players.len(),
+          "max": settings.maxPlayers,
+          "list": players,
+        },
+        "uptime": minecraft.map(|minecraft| minecraft.uptime().as_secs()),
+        "motd": motd,
+      })
+    ),
+  )
+}
+
+async fn api_wake(
+  WakeAccess(identity): WakeAccess,
+  State(state): State<Arc<WebState>>,
+  ConnectInfo(peer): ConnectInfo<SocketAddr>
+) -> ApiResponse {
+  if state.is_refused(&peer) {
+    return api_error(StatusCode::FORBIDDEN, "Your address is not allowed to wake the server");
+  }
+  let container: Arc<SleepingContainer> = match state.container.upgrade() {
+    Some(container) => container,
+    None => {
+      return gone_error();
+    }
+  };
+
+  info!("[Web] Wake up requested by {} from the API", identity.name);
+  if container.wake_up(web_player()) {
+    (StatusCode::ACCEPTED, Json(json!({ "status": format!("{:?}", container.get_status()) })))
+  } else {
+    api_error(StatusCode::CONFLICT, &format!("The server is {:?}, not sleeping", container.get_status()))
+  }
+}
+
+async fn api_stop(AdminAccess(identity): AdminAccess, State(state): State<Arc<WebState>>) -> ApiResponse {
+  let container: Arc<SleepingContainer> = match state.container.upgrade() {
+    Some(container) => container,
+    None => {
+      return gone_error();
+    }
+  };
+
+  info!("[Web] Stop requested by {} from the API", identity.name);
+  match container.stop_minecraft().await {
+    Ok(_) => (StatusCode::ACCEPTED, Json(json!({ "status": format!("{:?}", container.get_status()) }))),
+    Err(e) => not_running_error(e),
+  }
+}
+
+async fn api_command(
+  AdminAccess(identity): AdminAccess,
+  State(state): State<Arc<WebState>>,
+  Json(request): Json<CommandRequest>
+) -> ApiResponse {
+  let container: Arc<SleepingContainer> = match state.container.upgrade() {
+    Some(container) => container,
+    None => {
+      return gone_error();
+    }
+  };
+
+  let command: &str = request.command.trim();
+  if command.is_empty() {
+    return api_error(StatusCode::BAD_REQUEST, "The command is empty");
+  }
+  info!("[Web] Command from {} through the API: {}", identity.name, command);
+  match container.send_command(command).await {
+    Ok(_) => (StatusCode::OK, Json(json!({ "command": command }))),
+    Err(e) => not_running_error(e),
+  }
+}
+
+fn log_levels() -> Value {
+  json!({
+    "terminal": get_log_level(LogOutput::Terminal).to_string().to_lowercase(),
+    "file": get_log_level(LogOutput::File).to_string().to_lowercase(),
+  })
+}
+
+async fn api_log_level(_: AdminAccess) -> ApiResponse {
+  (StatusCode::OK, Json(log_levels()))
+}
+
+async fn api_set_log_level(AdminAccess(identity): AdminAccess, Json(request): Json<LogLevelRequest>) -> ApiResponse {
+  let mut changes: Vec<(LogOutput, LevelFilter)> = Vec::new();
+  for (output, level) in [(LogOutput::Terminal, request.terminal), (LogOutput::File, request.file)] {
+    if let Some(level) = level {
+      match level.parse::<LevelFilter>() {
+        Ok(level) => changes.push((output, level)),
+        Err(_) => {
+          return api_error(StatusCode::BAD_REQUEST, &format!("Unknown log level {:?}", level));
+        }
+      }
+    }
+  }
+
+  // Nothing changes unless every level is valid
+  for (output, level) in changes {
+    set_log_level(output, level);
+    info!("[Web] {} set the log level of the {} to {} through the API", identity.name, output.name(), level);
+  }
+  (StatusCode::OK, Json(log_levels()))
+}
+
+async fn api_console(
+  ReadAccess(identity): ReadAccess,
+  State(state): State<Arc<WebState>>,
+  upgrade: WebSocketUpgrade
+) -> Response {
+  let console: Arc<Console> = match state.container.upgrade() {
+    Some(container) => container.get_console(),
+    None => {
+      return gone_error().into_response();
+    }
+  };
+  upgrade.on_upgrade(move |socket| console_session(socket, state, console, identity))
+}
+
+/// Sends the scrollback then every new line of the console, and runs the commands sent back by admins.
+async fn console_session(mut socket: WebSocket, state: Arc<WebState>, console: Arc<Console>, identity: Identity) {
+  debug!("[Web] {} opened the console", identity.name);
+  let (scrollback, mut lines) = console.subscribe();
+  for line in scrollback {
+    if socket.send(Message::Text(line)).await.is_err() {
+      return;
+    }
+  }
+
+  loop {
+    let answer: Option<String> = tokio::select! {
+      _ = state.closing.cancelled() => {
+        let _ = socket.send(Message::Close(None)).await;
+        break;
+      }
+      line = lines.recv() => {
+        match line {
+          Ok(line) => Some(line),
+          Err(RecvError::Lagged(skipped)) => Some(format!("[{} lines skipped]", skipped)),
+          Err(RecvError::Closed) => break,
+        }
+      }
+      message = socket.recv() => {
+        match message {
+          Some(Ok(Message::Text(command))) => console_command(&state, &identity, &command).await,
+          Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+          Some(Ok(_)) => None,
+        }
+      }
+    };
+
+    if let Some(answer) = answer {
+      if socket.send(Message::Text(answer)).await.is_err() {
+        break;
+      }
+    }
+  }
+  debug!("[Web] {} closed the console", identity.name);
+}
+
+/// Runs a command typed in the console, returns what to answer if it wasn't sent. Sent commands are echoed by
+/// the console itself.
+async fn console_command(state: &WebState, identity: &Identity, command: &str) -> Option<String> {
+  // Without webAuth nobody has proved who they are, whatever their identity says
+  if !state.auth.is_enabled() || identity.role != WebRole::Admin {
+    warn!("[Web] {} is not allowed to send console commands", identity.name);
+    return Some(String::from("Only admins can send commands"));
+  }
+  let command: &str = command.trim();
+  if command.is_empty() {
+    return None;
+  }
+  info!("[Web] Command from {} through the console: {}", identity.name, command);
+  if let Some(answer) = log_level_command(command) {
+    info!("[Web] {}", answer);
+    return Some(answer);
+  }
+  let container: Arc<SleepingContainer> = state.container.upgrade()?;
+
+  match container.send_command(command).await {
+    Ok(_) => None,
+    Err(e) => Some(format!("Failed to send the command: {}", e)),
+  }
+}
+
+/// Streams the server events until the client goes away or the web server closes.
+async fn api_events(_: ReadAccess, State(state): State<Arc<WebState>>) -> Response {
+  let events: Receiver<ServerEvent> = match state.container.upgrade() {
+    Some(container) => container.subscribe_events(),
+    None => {
+      return gone_error().into_response();
+    }
+  };
+
+  let stream = stream::unfold((events, state), |(mut events, state)| async move {
+    // Events first, so the ones leading to the shutdown still go out
+    let event: Event = tokio::select! {
+      biased;
+      event = events.recv() => {
+        match event {
+          Ok(event) => {
+            Event::default()
+              .event(event.name())
+              .json_data(&event)
+              .unwrap_or_else(|e| Event::default().event("error").data(e.to_string()))
+          }
+          Err(RecvError::Lagged(skipped)) => Event::default().event("lagged").data(skipped.to_string()),
+          Err(RecvError::Closed) => {
+            return None;
+          }
+        }
+      }
+      _ = state.closing.cancelled() => {
+        return None;
+      }
+    };
+    Some((Ok::<Event, Infallible>(event), (events, state)))
+  });
+
+  Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// Prometheus metrics, scraped with a `readOnly` token when `webAuth` is set.
+async fn metrics(_: ReadAccess, State(state): State<Arc<WebState>>) -> Response {
+  let minecraft: Option<Arc<MinecraftProcess>> = state.container.upgrade().and_then(|container| container.get_minecraft());
+  let online_players: usize = minecraft.as_ref().map(|minecraft| minecraft.get_players().len()).unwrap_or(0);
+  let body: String = METRICS.render(online_players, minecraft.and_then(|minecraft| minecraft.id()));
+
+  ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}